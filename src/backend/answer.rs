@@ -0,0 +1,44 @@
+use simplelog::warn;
+
+use super::ConversationEvent;
+
+/// The text of an answer, built from the events of either update mode
+#[derive(Debug, Default)]
+pub struct AnswerText {
+    text: String,
+}
+
+impl AnswerText {
+    /// Apply the event, returns the text appended to the answer if any.
+    /// Rewritten text is taken as is, but can't be reported as appended
+    pub fn apply(&mut self, event: &ConversationEvent) -> Option<String> {
+        match event {
+            ConversationEvent::Delta(delta) => {
+                self.text.push_str(delta);
+                Some(delta.clone())
+            }
+            ConversationEvent::Update(text)
+            | ConversationEvent::Rewrite(text)
+            | ConversationEvent::Retracted { apology: text, .. } => {
+                let appended = text
+                    .strip_prefix(self.text.as_str())
+                    .filter(|appended| !appended.is_empty())
+                    .map(String::from);
+                if appended.is_none() && !text.starts_with(self.text.as_str()) {
+                    warn!("answer rewritten, streamed text can't be changed");
+                }
+                self.text = text.clone();
+                appended
+            }
+            _ => None,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_text(self) -> String {
+        self.text
+    }
+}
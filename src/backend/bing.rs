@@ -0,0 +1,135 @@
+use futures::{future::BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
+
+use crate::bing::{
+    protocol::Throttling, AccountHandle, AccountPool, ClientConfig, Conversation,
+    ConversationState, ConversationStyle, CookieJar, Error,
+};
+
+use super::{ChatBackend, ChatConversation, EventStream};
+
+/// Conversations with Bing, created with the account pool if it has accounts
+/// and with the single cookie jar otherwise
+pub struct BingBackend {
+    cookies: CookieJar,
+    pool: AccountPool,
+    config: ClientConfig,
+}
+
+impl BingBackend {
+    pub fn new<C: Into<CookieJar>>(cookies: C, pool: AccountPool, config: ClientConfig) -> Self {
+        Self {
+            cookies: cookies.into(),
+            pool,
+            config,
+        }
+    }
+}
+
+/// A Bing conversation as it's saved
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    #[serde(flatten)]
+    state: ConversationState,
+    /// Name of the pool account the conversation was created with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    account: Option<String>,
+}
+
+impl ChatBackend for BingBackend {
+    fn name(&self) -> &str {
+        "Bing"
+    }
+
+    fn create_conversation(&self) -> BoxFuture<'_, Result<Box<dyn ChatConversation>, Error>> {
+        async move {
+            let conversation = if self.pool.is_empty() {
+                BingConversation {
+                    conversation: Conversation::with_config(
+                        self.cookies.clone(),
+                        self.config.clone(),
+                    )
+                    .await?,
+                    account: None,
+                }
+            } else {
+                let (conversation, account) =
+                    self.pool.create_conversation(self.config.clone()).await?;
+                BingConversation {
+                    conversation,
+                    account: Some(account),
+                }
+            };
+            Ok(Box::new(conversation) as Box<dyn ChatConversation>)
+        }
+        .boxed()
+    }
+
+    fn restore_conversation(
+        &self,
+        state: serde_json::Value,
+    ) -> Result<Box<dyn ChatConversation>, Error> {
+        let saved: SavedState = serde_json::from_value(state)?;
+        Ok(Box::new(BingConversation {
+            conversation: Conversation::restore(saved.state, self.config.clone())?,
+            account: saved.account.and_then(|name| self.pool.handle(&name)),
+        }))
+    }
+}
+
+/// A Bing conversation and the pool account it was created with
+pub struct BingConversation {
+    conversation: Conversation,
+    account: Option<AccountHandle>,
+}
+
+impl ChatConversation for BingConversation {
+    fn id(&self) -> &str {
+        self.conversation.id()
+    }
+
+    fn style(&self) -> ConversationStyle {
+        self.conversation.style()
+    }
+
+    fn throttling(&self) -> Option<Throttling> {
+        self.conversation.throttling()
+    }
+
+    fn send_message(
+        &mut self,
+        text: String,
+        style: ConversationStyle,
+    ) -> BoxFuture<'_, Result<EventStream, Error>> {
+        self.conversation
+            .send_message_with_style(text, style)
+            .boxed()
+    }
+
+    fn cancel(&self) -> bool {
+        self.conversation.cancel()
+    }
+
+    fn save(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(SavedState {
+            state: self.conversation.state(),
+            account: self
+                .account
+                .as_ref()
+                .map(|account| account.name().to_string()),
+        })
+        .ok()
+    }
+
+    fn label(&self) -> Option<String> {
+        self.account
+            .as_ref()
+            .map(|account| format!("Account: {}", account.name()))
+    }
+
+    fn report_error(&self, error: &Error) {
+        if let Some(account) = &self.account {
+            account.report_error(error);
+        }
+    }
+}
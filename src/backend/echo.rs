@@ -0,0 +1,147 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use futures::{future::BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+
+use super::{
+    ChatBackend, ChatConversation, ConversationEvent, ConversationStyle, Error, EventStream,
+    UpdateMode,
+};
+
+/// A backend answering every message with the message itself, word by word.
+/// It's deterministic, so it's handy for tests and for working on frontends offline
+#[derive(Debug, Clone, Default)]
+pub struct EchoBackend {
+    /// Delay before every word, `None` answers at once
+    delay: Option<Duration>,
+    update_mode: UpdateMode,
+    /// Number of conversations created, used for their ids
+    created: Arc<AtomicUsize>,
+}
+
+impl EchoBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait before every word of the answer, so it can be cancelled
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Report the answer with `Update` or `Delta` events
+    pub fn with_update_mode(mut self, update_mode: UpdateMode) -> Self {
+        self.update_mode = update_mode;
+        self
+    }
+
+    fn conversation(&self, state: EchoState) -> EchoConversation {
+        EchoConversation {
+            state,
+            delay: self.delay,
+            update_mode: self.update_mode,
+            cancel: Arc::default(),
+        }
+    }
+}
+
+/// An echo conversation as it's saved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EchoState {
+    id: String,
+    /// Number of messages sent
+    turns: u32,
+}
+
+impl ChatBackend for EchoBackend {
+    fn name(&self) -> &str {
+        "Echo"
+    }
+
+    fn create_conversation(&self) -> BoxFuture<'_, Result<Box<dyn ChatConversation>, Error>> {
+        let id = format!("echo-{}", self.created.fetch_add(1, Ordering::SeqCst) + 1);
+        let conversation = self.conversation(EchoState { id, turns: 0 });
+        async move { Ok(Box::new(conversation) as Box<dyn ChatConversation>) }.boxed()
+    }
+
+    fn restore_conversation(
+        &self,
+        state: serde_json::Value,
+    ) -> Result<Box<dyn ChatConversation>, Error> {
+        Ok(Box::new(self.conversation(serde_json::from_value(state)?)))
+    }
+}
+
+pub struct EchoConversation {
+    state: EchoState,
+    delay: Option<Duration>,
+    update_mode: UpdateMode,
+    /// Stops the running answer
+    cancel: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+}
+
+impl ChatConversation for EchoConversation {
+    fn id(&self) -> &str {
+        &self.state.id
+    }
+
+    fn style(&self) -> ConversationStyle {
+        ConversationStyle::default()
+    }
+
+    fn send_message(
+        &mut self,
+        text: String,
+        _style: ConversationStyle,
+    ) -> BoxFuture<'_, Result<EventStream, Error>> {
+        self.state.turns += 1;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (cancel, mut cancelled) = oneshot::channel();
+        *self.cancel.lock().unwrap() = Some(cancel);
+
+        let delay = self.delay;
+        let update_mode = self.update_mode;
+        tokio::spawn(async move {
+            let mut answer = String::new();
+            for word in text.split_inclusive(char::is_whitespace) {
+                if let Some(delay) = delay {
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = &mut cancelled => {
+                            tx.send(ConversationEvent::Cancelled).ok();
+                            return;
+                        }
+                    }
+                }
+                answer.push_str(word);
+                let event = match update_mode {
+                    UpdateMode::Snapshot => ConversationEvent::Update(answer.clone()),
+                    UpdateMode::Delta => ConversationEvent::Delta(word.to_string()),
+                };
+                tx.send(event).ok();
+            }
+            tx.send(ConversationEvent::Complete).ok();
+        });
+
+        async move { Ok(rx) }.boxed()
+    }
+
+    fn cancel(&self) -> bool {
+        match self.cancel.lock().unwrap().take() {
+            Some(cancel) => cancel.send(()).is_ok(),
+            None => false,
+        }
+    }
+
+    fn save(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(&self.state).ok()
+    }
+}
@@ -0,0 +1,122 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("no cookies found for the create endpoint")]
+    CookieNotFound,
+
+    #[error("invalid cookies: {0}")]
+    InvalidCookies(String),
+
+    #[error("no healthy account in the pool")]
+    NoHealthyAccount,
+
+    #[error("not connected")]
+    NotConnected,
+
+    #[error("init error, failed to read first message")]
+    Init,
+
+    #[error("websocket connection is busy")]
+    WsBusy,
+
+    #[error("timed out creating the conversation")]
+    CreateTimeout,
+
+    #[error("timed out connecting to the chat hub")]
+    HandshakeTimeout,
+
+    #[error("timed out waiting for the first token of the answer")]
+    FirstTokenTimeout,
+
+    #[error("timed out waiting for the next update of the answer")]
+    IdleTimeout,
+
+    #[error("connection closed before the answer was complete")]
+    ConnectionClosed,
+
+    #[error("conversation turn limit reached ({0} messages)")]
+    TurnLimitReached(u32),
+
+    #[error("the conversation has expired")]
+    ConversationExpired,
+
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("throttled: {0}")]
+    Throttled(String),
+
+    #[error("invalid session: {0}")]
+    InvalidSession(String),
+
+    #[error("captcha challenge: {0}")]
+    CaptchaChallenge(String),
+
+    #[error("forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("server error {value}: {message}")]
+    Server { value: String, message: String },
+
+    #[error("unknown conversation style {0}")]
+    UnknownStyle(String),
+
+    #[error("invalid header: {0}")]
+    InvalidHeader(String),
+
+    #[error("tls error: {0}")]
+    Tls(String),
+
+    #[error("proxy error: {0}")]
+    Proxy(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+
+    #[error(transparent)]
+    Ws(Box<async_tungstenite::tungstenite::Error>),
+}
+
+impl From<async_tungstenite::tungstenite::Error> for Error {
+    fn from(err: async_tungstenite::tungstenite::Error) -> Self {
+        Self::Ws(Box::new(err))
+    }
+}
+
+impl Error {
+    /// Returns true if the failure may go away by reconnecting,
+    /// a first token timeout isn't as the invocation was already sent and counted
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::Init
+                | Self::ConnectionClosed
+                | Self::HandshakeTimeout
+                | Self::Io(_)
+                | Self::Ws(_)
+        )
+    }
+
+    /// Returns true if the account is throttled or unauthorized,
+    /// so another account should be used
+    pub fn is_account_failure(&self) -> bool {
+        match self {
+            Self::CookieNotFound
+            | Self::Unauthorized(_)
+            | Self::Throttled(_)
+            | Self::CaptchaChallenge(_)
+            | Self::Forbidden(_) => true,
+            Self::Http(err) => err
+                .status()
+                .is_some_and(|status| matches!(status.as_u16(), 401 | 403 | 429)),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::Error;
+
+/// An event that occurs during a conversation
+#[derive(Debug)]
+pub enum ConversationEvent {
+    /// The whole text of the answer so far, in `UpdateMode::Snapshot`
+    Update(String),
+    /// Text appended to the answer, in `UpdateMode::Delta`
+    Delta(String),
+    /// The server changed earlier text, this is the whole new text, in `UpdateMode::Delta`
+    Rewrite(String),
+    /// Web sources the answer cites, in the order of the `[^N^]` markers
+    Sources(Vec<SourceAttribution>),
+    /// Follow-up messages the user may send next
+    Suggestions(Vec<String>),
+    /// Turn counters reported by the server
+    Throttling(Throttling),
+    /// The chatbot is searching or preparing the answer
+    Progress(Activity),
+    /// The answer was withdrawn and replaced with `apology`,
+    /// `original` is the text received before
+    Retracted {
+        original: String,
+        apology: String,
+    },
+    /// The answer was stopped by `ChatConversation::cancel`
+    Cancelled,
+    /// The connection failed for good, `partial` is the text received so far
+    Failed {
+        partial: String,
+        error: Error,
+    },
+    Complete,
+}
+
+/// How the text of a streaming answer is reported
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UpdateMode {
+    /// `ConversationEvent::Update` with the whole text received so far
+    #[default]
+    Snapshot,
+    /// `ConversationEvent::Delta` with the newly appended text,
+    /// or `ConversationEvent::Rewrite` if the server changed earlier text
+    Delta,
+}
+
+/// What the chatbot is doing before and while it writes the answer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Activity {
+    /// Searching the web for the query
+    Searching(String),
+    /// Search results were received
+    SearchResults,
+    /// A status message like "Generating answers for you..."
+    Loading(String),
+}
+
+impl fmt::Display for Activity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Searching(query) => write!(f, "Searching for {}…", query),
+            Self::SearchResults => write!(f, "Reading search results…"),
+            Self::Loading(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// A web source the answer is based on
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceAttribution {
+    #[serde(default)]
+    pub provider_display_name: String,
+    #[serde(default)]
+    pub see_more_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_query: Option<String>,
+}
+
+/// Turn counters of the conversation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Throttling {
+    pub max_num_user_messages_in_conversation: u32,
+    pub num_user_messages_in_conversation: u32,
+}
+
+impl Throttling {
+    /// Returns true if no more user messages are accepted in the conversation
+    pub fn is_exhausted(&self) -> bool {
+        self.max_num_user_messages_in_conversation > 0
+            && self.num_user_messages_in_conversation >= self.max_num_user_messages_in_conversation
+    }
+}
@@ -0,0 +1,76 @@
+use futures::future::BoxFuture;
+use tokio::sync::mpsc;
+
+mod error;
+pub use error::*;
+
+mod event;
+pub use event::*;
+
+mod style;
+pub use style::*;
+
+mod answer;
+pub use answer::*;
+
+mod bing;
+pub use self::bing::*;
+
+mod echo;
+pub use echo::*;
+
+/// Events of a single answer, closed once the answer is complete
+pub type EventStream = mpsc::UnboundedReceiver<ConversationEvent>;
+
+/// A chatbot service conversations are created with
+pub trait ChatBackend: Send + Sync {
+    /// Name of the backend, shown to the user
+    fn name(&self) -> &str;
+
+    /// Create a new conversation
+    fn create_conversation(&self) -> BoxFuture<'_, Result<Box<dyn ChatConversation>, Error>>;
+
+    /// Rebuild a conversation from the state returned by `ChatConversation::save`
+    fn restore_conversation(
+        &self,
+        state: serde_json::Value,
+    ) -> Result<Box<dyn ChatConversation>, Error>;
+}
+
+/// A conversation created by a `ChatBackend`
+pub trait ChatConversation: Send {
+    fn id(&self) -> &str;
+
+    /// Style used when the caller has no preference
+    fn style(&self) -> ConversationStyle;
+
+    /// Last turn counters reported by the service, if any
+    fn throttling(&self) -> Option<Throttling> {
+        None
+    }
+
+    /// Send a message, returns the events of the answer
+    fn send_message(
+        &mut self,
+        text: String,
+        style: ConversationStyle,
+    ) -> BoxFuture<'_, Result<EventStream, Error>>;
+
+    /// Stop the answer that is being received
+    /// Returns false if there was nothing to stop
+    fn cancel(&self) -> bool;
+
+    /// State to save so the conversation can be restored,
+    /// `None` if it can't outlive the process
+    fn save(&self) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// Extra information shown next to the conversation, like the account it uses
+    fn label(&self) -> Option<String> {
+        None
+    }
+
+    /// Called with the errors of the conversation, e.g. to rotate accounts
+    fn report_error(&self, _error: &Error) {}
+}
@@ -0,0 +1,44 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::Error;
+
+/// Tone of the chatbot's answers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConversationStyle {
+    /// Original and imaginative answers
+    Creative,
+    /// Informative and friendly answers
+    #[default]
+    Balanced,
+    /// Concise and straightforward answers
+    Precise,
+}
+
+impl ConversationStyle {
+    /// All available styles
+    pub const ALL: [ConversationStyle; 3] = [Self::Creative, Self::Balanced, Self::Precise];
+}
+
+impl fmt::Display for ConversationStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Creative => "Creative",
+            Self::Balanced => "Balanced",
+            Self::Precise => "Precise",
+        })
+    }
+}
+
+impl FromStr for ConversationStyle {
+    type Err = Error;
+
+    /// Parse a style name, ignoring the case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|style| style.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| Error::UnknownStyle(s.to_string()))
+    }
+}
@@ -0,0 +1,20 @@
+use super::{protocol::BotMessage, Activity};
+
+impl Activity {
+    /// Classify an internal message, `None` if it's not a known one
+    pub fn from_message(message: &BotMessage) -> Option<Self> {
+        let text = message
+            .text
+            .as_deref()
+            .or(message.hidden_text.as_deref())
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        match message.message_type.as_deref()? {
+            "InternalSearchQuery" => Some(Self::Searching(text)),
+            "InternalSearchResult" => Some(Self::SearchResults),
+            "InternalLoaderMessage" => Some(Self::Loading(text)),
+            _ => None,
+        }
+    }
+}
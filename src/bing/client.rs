@@ -1,21 +1,27 @@
-use std::sync::Arc;
-
-use async_tungstenite::tungstenite::{self, Message};
-use futures::{Sink, SinkExt, StreamExt};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 
 use reqwest::Method;
-use serde::Deserialize;
-use serde_json::json;
+use serde::{Deserialize, Serialize};
 use simplelog::{error, trace, warn};
-use tokio::sync::{mpsc, Mutex};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::Instant,
+};
+use url::Url;
 use uuid::Uuid;
 
-use super::Error;
-
-// Multiple objects in a single WS message are delimited by this character
-const WS_DELIMITER: u8 = 0x1e;
-
-type WsWriter = Box<dyn Sink<Message, Error = tungstenite::Error> + Unpin + Send>;
+use super::{
+    hub::{self, Backoff, Hub, IdleHub},
+    protocol::{
+        BotMessage, CancelInvocation, ChatRequest, ChatResult, Frame, Participant,
+        StreamInvocation, Throttling, UserMessage,
+    },
+    Activity, ClientConfig, ConversationEvent, ConversationStyle, CookieJar, Error, UpdateMode,
+};
 
 /// A conversation with the Bing chatbot
 pub struct Conversation {
@@ -23,58 +29,112 @@ pub struct Conversation {
     client_id: String,
     signature: String,
     is_start_of_session: bool,
-    writer: Arc<Mutex<Option<WsWriter>>>,
+    style: ConversationStyle,
+    config: ClientConfig,
+    shared: Arc<SharedState>,
+}
+
+/// State shared between a conversation and its running turn
+#[derive(Default)]
+struct SharedState {
+    /// Turn counters reported by the server
+    throttling: std::sync::Mutex<Option<Throttling>>,
+    /// True while an answer is being received
+    busy: AtomicBool,
+    /// Connection kept open between turns in persistent mode
+    idle_hub: std::sync::Mutex<Option<IdleHub>>,
+    /// Stops the running turn
+    cancel: std::sync::Mutex<Option<oneshot::Sender<()>>>,
+    /// When the server forgets the conversation, as reported with the last answer
+    expires_at: std::sync::Mutex<Option<OffsetDateTime>>,
+}
+
+/// Everything needed to continue a conversation later, see `Conversation::restore`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConversationState {
+    pub id: String,
+    pub client_id: String,
+    pub signature: String,
+    pub is_start_of_session: bool,
+    #[serde(default)]
+    pub style: ConversationStyle,
+    #[serde(default)]
+    pub throttling: Option<Throttling>,
+    /// `None` until the server sent the first answer
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+impl ConversationState {
+    /// Returns true if the server no longer knows the conversation
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= OffsetDateTime::now_utc())
+    }
 }
 
 /// The result of creating a conversation
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct ConversationResult {
+    #[serde(default)]
     conversation_id: String,
+    #[serde(default)]
     client_id: String,
+    #[serde(default)]
     conversation_signature: String,
-}
-
-/// An event that occurs during a conversation
-#[derive(Debug)]
-pub enum ConversationEvent {
-    Update(String),
-    Complete,
+    #[serde(default)]
+    result: Option<ChatResult>,
 }
 
 impl Conversation {
     /// Create a new conversation
-    pub async fn new<C: Into<String>>(cookie: C) -> Result<Self, Error> {
-        let mut cookie: String = cookie.into();
-
-        // Find the _U cookie if there are multiple
-        if cookie.contains(';') {
-            match cookie
-                .split(';')
-                .filter_map(|pair| pair.trim().split_once('='))
-                .map(|(key, value)| (key.to_lowercase(), value))
-                .find(|(key, _)| key == "_u")
-                .map(|(_, value)| value)
-            {
-                Some(x) => cookie = x.to_string(),
-                None => return Err(Error::CookieNotFound),
-            }
+    pub async fn new<C: Into<CookieJar>>(cookies: C) -> Result<Self, Error> {
+        Self::with_config(cookies, ClientConfig::default()).await
+    }
+
+    /// Create a new conversation using the given client configuration
+    pub async fn with_config<C: Into<CookieJar>>(
+        cookies: C,
+        config: ClientConfig,
+    ) -> Result<Self, Error> {
+        let cookies: CookieJar = cookies.into();
+        let host = Url::parse(&config.create_url)
+            .ok()
+            .and_then(|url| url.host_str().map(String::from))
+            .unwrap_or_default();
+        let cookie = cookies.header_for(&host).ok_or(Error::CookieNotFound)?;
+        if cookies.get("_U").is_none() {
+            warn!("cookie \"_U\" not found, the request may be unauthorized");
         }
-        cookie = format!("_U={}", cookie);
-
-        let response: ConversationResult = reqwest::Client::default()
-            .request(
-                Method::GET,
-                "https://www.bing.com/turing/conversation/create",
-            )
-            .header("cookie", &cookie)
-            // Hacky trick to bypass some errors
-            // Thanks to Reddit :D
-            .header("x-forwarded-for", "1.1.1.1")
-            .send()
-            .await?
-            .json()
+
+        let mut request = config
+            .http_client()?
+            .request(Method::GET, &config.create_url)
+            .header("cookie", &cookie);
+        for (name, value) in config.region.headers() {
+            request = request.header(name, value);
+        }
+        let response: ConversationResult =
+            hub::timeout(config.timeouts.create, Error::CreateTimeout, async {
+                let response = request.send().await?;
+                // A failure status explains a body that isn't a conversation better
+                let status_error = response.error_for_status_ref().err();
+                match response.json().await {
+                    Ok(response) => Ok(response),
+                    Err(err) => Err(status_error.unwrap_or(err).into()),
+                }
+            })
             .await?;
+        if let Some(err) = response.result.as_ref().and_then(Error::from_result) {
+            return Err(err);
+        }
+        if response.conversation_id.is_empty() {
+            return Err(Error::Server {
+                value: "Unknown".to_string(),
+                message: "no conversation in the response".to_string(),
+            });
+        }
         trace!(
             "conversation created: <green>{}</>",
             response.conversation_id
@@ -85,10 +145,48 @@ impl Conversation {
             client_id: response.client_id,
             signature: response.conversation_signature,
             is_start_of_session: true,
-            writer: Arc::new(Mutex::new(None)),
+            style: ConversationStyle::default(),
+            config,
+            shared: Arc::default(),
+        })
+    }
+
+    /// Rebuild a conversation from a saved state
+    /// Fails with `Error::ConversationExpired` if the server already forgot it
+    pub fn restore(state: ConversationState, config: ClientConfig) -> Result<Self, Error> {
+        if state.is_expired() {
+            return Err(Error::ConversationExpired);
+        }
+        trace!("conversation restored: <green>{}</>", state.id);
+
+        Ok(Self {
+            id: state.id,
+            client_id: state.client_id,
+            signature: state.signature,
+            is_start_of_session: state.is_start_of_session,
+            style: state.style,
+            config,
+            shared: Arc::new(SharedState {
+                throttling: std::sync::Mutex::new(state.throttling),
+                expires_at: std::sync::Mutex::new(state.expires_at),
+                ..Default::default()
+            }),
         })
     }
 
+    /// Get the state to save, so the conversation can be restored later
+    pub fn state(&self) -> ConversationState {
+        ConversationState {
+            id: self.id.clone(),
+            client_id: self.client_id.clone(),
+            signature: self.signature.clone(),
+            is_start_of_session: self.is_start_of_session,
+            style: self.style,
+            throttling: self.throttling(),
+            expires_at: self.expires_at(),
+        }
+    }
+
     /// Get the conversation ID
     pub fn id(&self) -> &str {
         &self.id
@@ -109,9 +207,43 @@ impl Conversation {
         self.is_start_of_session
     }
 
-    /// Returns true if there's an active websocket connection
+    /// Get the default style of the conversation
+    pub fn style(&self) -> ConversationStyle {
+        self.style
+    }
+
+    /// Set the default style used by `send_message`
+    pub fn set_style(&mut self, style: ConversationStyle) {
+        self.style = style;
+    }
+
+    /// Get the client configuration
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// Get the last turn counters reported by the server, if any
+    pub fn throttling(&self) -> Option<Throttling> {
+        *self.shared.throttling.lock().unwrap()
+    }
+
+    /// Get the time the server forgets the conversation, if known
+    pub fn expires_at(&self) -> Option<OffsetDateTime> {
+        *self.shared.expires_at.lock().unwrap()
+    }
+
+    /// Stop the answer that is being received
+    /// Returns false if there was nothing to stop
+    pub fn cancel(&self) -> bool {
+        match self.shared.cancel.lock().unwrap().take() {
+            Some(cancel) => cancel.send(()).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Returns true while an answer is being received
     pub async fn is_busy(&self) -> bool {
-        self.writer.lock().await.is_some()
+        self.shared.busy.load(Ordering::SeqCst)
     }
 
     /// Send a message to the chatbot
@@ -120,163 +252,405 @@ impl Conversation {
         &mut self,
         text: T,
     ) -> Result<mpsc::UnboundedReceiver<ConversationEvent>, Error> {
-        // If the writer is some, then websocket is already open and waiting for a message
-        if self.writer.lock().await.is_some() {
+        self.send_message_with_style(text, self.style).await
+    }
+
+    /// Send a message to the chatbot using the given style for this message only
+    /// Returns a `Receiver` that will receive conversation events
+    pub async fn send_message_with_style<T: Into<String>>(
+        &mut self,
+        text: T,
+        style: ConversationStyle,
+    ) -> Result<mpsc::UnboundedReceiver<ConversationEvent>, Error> {
+        if self.shared.busy.load(Ordering::SeqCst) {
             return Err(Error::WsBusy);
         }
 
-        let (stream, _) =
-            async_tungstenite::tokio::connect_async("wss://sydney.bing.com/sydney/ChatHub").await?;
-        let (write, mut read) = stream.split();
-        *self.writer.lock().await = Some(Box::new(write));
-
-        // Init message
-        self.write(json!({
-            "protocol": "json",
-            "version": 1,
-        }))
-        .await?;
-
-        // Idk what this is
-        read.next().await.ok_or(Error::Init)?.ok();
-        self.write(json!({
-            "type": 6
-        }))
-        .await?;
-
-        // Send the actual message
-        self.write(json!({
-            "arguments": [
-                {
-                    "source": "cib",
-                    // TODO: configure these by user settings
-                    "optionsSets": [
-                        "nlu_direct_response_filter",
-                        "deepleo",
-                        "disable_emoji_spoken_text",
-                        "responsible_ai_policy_235",
-                        "enablemm",
-                        "galileo",
-                        "newspoleansgnd",
-                        "cachewriteext",
-                        "e2ecachewrite",
-                        "dl_edge_prompt",
-                        "dv3sugg"
-                    ],
-                    "isStartOfSession": self.is_start_of_session,
-                    "message": {
-                        "author": "user",
-                        "inputMethod": "Keyboard",
-                        "text": text.into(),
-                        "messageType": "Chat"
-                    },
-                    "conversationSignature": self.signature,
-                    "participant": {
-                        "id": self.client_id
-                    },
-                    "conversationId": self.id,
-                }
-            ],
-            "invocationId": Uuid::new_v4().to_string(),
-            "target": "chat",
-            "type": 4
-        }))
-        .await?;
+        if self
+            .expires_at()
+            .is_some_and(|expires_at| expires_at <= OffsetDateTime::now_utc())
+        {
+            return Err(Error::ConversationExpired);
+        }
+        if let Some(throttling) = self.throttling().filter(Throttling::is_exhausted) {
+            return Err(Error::TurnLimitReached(
+                throttling.max_num_user_messages_in_conversation,
+            ));
+        }
+
+        let invocation = Frame::StreamInvocation(StreamInvocation {
+            invocation_id: Uuid::new_v4().to_string(),
+            target: "chat".to_string(),
+            arguments: vec![ChatRequest {
+                source: "cib".to_string(),
+                options_sets: style.options_sets().into_iter().map(String::from).collect(),
+                is_start_of_session: self.is_start_of_session,
+                message: UserMessage {
+                    author: "user".to_string(),
+                    input_method: "Keyboard".to_string(),
+                    text: text.into(),
+                    message_type: "Chat".to_string(),
+                    locale: Some(self.config.region.locale.clone()),
+                    market: Some(self.config.region.market.clone()),
+                    region: Some(self.config.region.region.clone()),
+                    location: self.config.region.location.map(|l| l.to_string()),
+                    timestamp: self.config.region.timestamp(),
+                },
+                conversation_signature: self.signature.clone(),
+                participant: Participant {
+                    id: self.client_id.clone(),
+                },
+                conversation_id: self.id.clone(),
+            }],
+        });
+
+        // Reuse the idle connection if there's one, reconnect if it died
+        let idle_hub = self.shared.idle_hub.lock().unwrap().take();
+        let hub = match idle_hub {
+            Some(idle_hub) => match idle_hub.resume().await {
+                Some(mut hub) => match hub.send(&invocation).await {
+                    Ok(()) => Some(hub),
+                    Err(err) => {
+                        warn!("idle ws is dead, reconnecting: {}", err);
+                        None
+                    }
+                },
+                None => None,
+            },
+            None => None,
+        };
+        let hub = match hub {
+            Some(hub) => hub,
+            None => {
+                Hub::open(
+                    &self.config,
+                    &invocation,
+                    &mut Backoff::new(&self.config.retry),
+                )
+                .await?
+            }
+        };
         if self.is_start_of_session {
             self.is_start_of_session = false;
         }
 
         let (tx, rx) = mpsc::unbounded_channel();
+        let (cancel, cancelled) = oneshot::channel();
+        *self.shared.cancel.lock().unwrap() = Some(cancel);
+        self.shared.busy.store(true, Ordering::SeqCst);
+        tokio::spawn(read_turn(
+            hub,
+            invocation,
+            tx,
+            cancelled,
+            self.shared.clone(),
+            self.config.clone(),
+        ));
 
-        let writer_clone = self.writer.clone();
-        tokio::spawn(async move {
-            let writer_clone_inner = writer_clone.clone();
-            read.for_each(move |message| {
-                let writer_clone = writer_clone_inner.clone();
-                let tx = tx.clone();
-                async move {
-                    if let Ok(message) = message {
-                        if message.is_close() {
-                            trace!("ws closed");
-                            return;
-                        }
+        Ok(rx)
+    }
+}
 
-                        let message = message.into_text().unwrap();
-
-                        for object in message
-                            .split(WS_DELIMITER as char)
-                            .map(|obj| obj.trim())
-                            .filter(|obj| !obj.is_empty())
-                        {
-                            let object: serde_json::Value = match serde_json::from_str(object) {
-                                Ok(object) => object,
-                                Err(err) => {
-                                    error!("expected json, err: <red>{}</>", err);
-                                    continue;
-                                }
-                            };
+/// What was received during the current turn
+#[derive(Default)]
+struct TurnProgress {
+    /// Text of the answer received so far
+    partial: Option<String>,
+    /// True once the final answer was received
+    is_answered: bool,
+    /// True once the answer was replaced with an apology
+    is_retracted: bool,
+    /// Id of the last internal message reported as a progress event
+    last_activity: Option<String>,
+}
 
-                            let type_id = match object.get("type").and_then(|v| v.as_u64()) {
-                                Some(type_id) => type_id,
-                                None => {
-                                    error!("expected type id");
-                                    continue;
-                                }
-                            };
+impl TurnProgress {
+    /// Returns true the first time an internal message is seen,
+    /// the server resends it with every update until the next one
+    fn is_new_activity(&mut self, message: &BotMessage) -> bool {
+        let id = message
+            .message_id
+            .clone()
+            .or_else(|| message.text.clone())
+            .unwrap_or_default();
+        if self.last_activity.as_ref() == Some(&id) {
+            return false;
+        }
+        self.last_activity = Some(id);
+        true
+    }
 
-                            trace!("msg type_id = <yellow>{}</>", type_id);
-                            match type_id {
-                                1 => {
-                                    trace!("update message");
-                                    match object
-                                        .get("arguments")
-                                        .and_then(|v| v.get(0))
-                                        .and_then(|v| v.get("messages"))
-                                        .and_then(|v| v.get(0))
-                                        .and_then(|v| v.get("text"))
-                                        .and_then(|v| v.as_str())
-                                        .map(|s| s.trim().to_string())
-                                    {
-                                        Some(text) => {
-                                            tx.send(ConversationEvent::Update(text)).ok();
-                                        }
-                                        None => warn!("no text in update message"),
-                                    }
-                                }
-                                2 => {
-                                    trace!("complete message");
-                                    tx.send(ConversationEvent::Complete).ok();
-                                }
-                                3 => {
-                                    trace!("closing ws");
-                                    writer_clone.lock().await.take().unwrap().close().await.ok();
-                                }
-                                id => {
-                                    warn!("unknown type_id = <yellow>{}</>", id);
+    /// Event reporting the new text of the answer, `None` if nothing changed
+    fn update(&self, text: &str, mode: UpdateMode) -> Option<ConversationEvent> {
+        match mode {
+            UpdateMode::Snapshot => Some(ConversationEvent::Update(text.to_string())),
+            UpdateMode::Delta => {
+                let previous = self.partial.as_deref().unwrap_or_default();
+                match text.strip_prefix(previous) {
+                    Some("") => None,
+                    Some(delta) => Some(ConversationEvent::Delta(delta.to_string())),
+                    None => Some(ConversationEvent::Rewrite(text.to_string())),
+                }
+            }
+        }
+    }
+
+    /// Take the text received so far if `message` withdraws it
+    fn retract(&mut self, message: &BotMessage) -> Option<String> {
+        if self.is_retracted || !message.is_retraction() {
+            return None;
+        }
+        let original = self.partial.take().filter(|text| !text.is_empty())?;
+        self.is_retracted = true;
+        Some(original)
+    }
+}
+
+/// How reading an answer ended
+enum TurnEnd {
+    Completed,
+    Cancelled,
+    Dropped(Error),
+}
+
+/// Drive the current turn until the invocation completes, is cancelled or fails,
+/// then either keep the connection for the next turn or close it
+async fn read_turn(
+    mut hub: Hub,
+    invocation: Frame,
+    tx: mpsc::UnboundedSender<ConversationEvent>,
+    mut cancelled: oneshot::Receiver<()>,
+    shared: Arc<SharedState>,
+    config: ClientConfig,
+) {
+    let mut backoff = Backoff::new(&config.retry);
+    let mut progress = TurnProgress::default();
+    let end = loop {
+        match read_answer(
+            &mut hub,
+            &tx,
+            &mut cancelled,
+            &shared,
+            &config,
+            &mut progress,
+        )
+        .await
+        {
+            // Nothing was shown yet, so the message can be safely sent again
+            TurnEnd::Dropped(err)
+                if progress.partial.is_none() && !progress.is_answered && err.is_transient() =>
+            {
+                let delay = match backoff.next() {
+                    Some(delay) => delay,
+                    None => break TurnEnd::Dropped(err),
+                };
+                warn!(
+                    "ws dropped before the answer, retrying in {:?}: {}",
+                    delay, err
+                );
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = &mut cancelled => break TurnEnd::Cancelled,
+                }
+                match Hub::open(&config, &invocation, &mut backoff).await {
+                    Ok(new_hub) => hub = new_hub,
+                    Err(err) => break TurnEnd::Dropped(err),
+                }
+            }
+            end => break end,
+        }
+    };
+
+    match end {
+        TurnEnd::Completed if config.persistent => {
+            trace!("keeping ws open for the next turn");
+            *shared.idle_hub.lock().unwrap() = Some(hub.keep_alive(config.keepalive_interval));
+        }
+        TurnEnd::Completed => hub.close().await,
+        // The answer is already complete, only the completion frame is missing
+        TurnEnd::Dropped(_) if progress.is_answered => hub.close().await,
+        TurnEnd::Cancelled => {
+            trace!("cancelling invocation");
+            if let Frame::StreamInvocation(invocation) = invocation {
+                hub.send(&Frame::CancelInvocation(CancelInvocation {
+                    invocation_id: invocation.invocation_id,
+                }))
+                .await
+                .ok();
+            }
+            tx.send(ConversationEvent::Cancelled).ok();
+            hub.close().await;
+        }
+        TurnEnd::Dropped(error) => {
+            error!("answer failed: <red>{}</>", error);
+            tx.send(ConversationEvent::Failed {
+                partial: progress.partial.unwrap_or_default(),
+                error,
+            })
+            .ok();
+            hub.close().await;
+        }
+    }
+    shared.cancel.lock().unwrap().take();
+    shared.busy.store(false, Ordering::SeqCst);
+}
+
+/// Read the frames of a single connection and forward them as events
+async fn read_answer(
+    hub: &mut Hub,
+    tx: &mpsc::UnboundedSender<ConversationEvent>,
+    cancelled: &mut oneshot::Receiver<()>,
+    shared: &SharedState,
+    config: &ClientConfig,
+    progress: &mut TurnProgress,
+) -> TurnEnd {
+    let mut deadline = config.timeouts.first_token.map(|d| Instant::now() + d);
+    loop {
+        let frames = tokio::select! {
+            frames = hub.next_frames() => frames,
+            _ = hub::sleep_until(deadline) => {
+                return TurnEnd::Dropped(match progress.partial {
+                    Some(_) => Error::IdleTimeout,
+                    None => Error::FirstTokenTimeout,
+                });
+            }
+            _ = &mut *cancelled => return TurnEnd::Cancelled,
+            // Dropping the receiver also cancels the answer
+            _ = tx.closed() => {
+                trace!("event receiver dropped");
+                return TurnEnd::Cancelled;
+            }
+        };
+        let frames = match frames {
+            Some(Ok(frames)) => frames,
+            Some(Err(err)) => return TurnEnd::Dropped(err),
+            None => return TurnEnd::Dropped(Error::ConnectionClosed),
+        };
+
+        for frame in frames {
+            trace!("msg type_id = <yellow>{}</>", frame.type_id());
+            match frame {
+                Frame::Invocation(invocation) => {
+                    trace!("update message");
+                    deadline = config.timeouts.idle.map(|d| Instant::now() + d);
+                    if let Some(update) = invocation.arguments.first() {
+                        update_throttling(shared, tx, update.throttling);
+                    }
+                    let Some(message) = invocation
+                        .arguments
+                        .first()
+                        .and_then(|update| update.messages.first())
+                    else {
+                        warn!("no message in update");
+                        continue;
+                    };
+                    // Searches and loader messages come before the answer itself
+                    if message.message_type.is_some() {
+                        match Activity::from_message(message) {
+                            Some(activity) if progress.is_new_activity(message) => {
+                                tx.send(ConversationEvent::Progress(activity)).ok();
+                            }
+                            Some(_) => {}
+                            None => trace!("internal message {:?}", message.message_type),
+                        }
+                        continue;
+                    }
+                    match message.text.as_deref().map(|text| text.trim().to_string()) {
+                        Some(text) => {
+                            let event = match progress.retract(message) {
+                                Some(original) => {
+                                    warn!("answer retracted");
+                                    Some(ConversationEvent::Retracted {
+                                        original,
+                                        apology: text.clone(),
+                                    })
                                 }
+                                None => progress.update(&text, config.update_mode),
+                            };
+                            progress.partial = Some(text);
+                            if let Some(event) = event {
+                                tx.send(event).ok();
                             }
                         }
+                        None => warn!("no text in update message"),
                     }
                 }
-            })
-            .await;
-            writer_clone.lock().await.take();
-        });
-
-        trace!("ws connected");
-        Ok(rx)
+                Frame::StreamItem(stream_item) => {
+                    trace!("complete message");
+                    update_throttling(shared, tx, stream_item.item.throttling);
+                    if let Some(err) = stream_item
+                        .item
+                        .result
+                        .as_ref()
+                        .and_then(Error::from_result)
+                    {
+                        return TurnEnd::Dropped(err);
+                    }
+                    if let Some(expiry) = &stream_item.item.conversation_expiry_time {
+                        match OffsetDateTime::parse(expiry, &Rfc3339) {
+                            Ok(expires_at) => *shared.expires_at.lock().unwrap() = Some(expires_at),
+                            Err(err) => {
+                                warn!("invalid conversation expiry time {}: {}", expiry, err)
+                            }
+                        }
+                    }
+                    if let Some(answer) = stream_item.item.answer() {
+                        // The apology may only come with the final answer
+                        if let Some(original) = progress.retract(answer) {
+                            warn!("answer retracted");
+                            let apology = answer.text.as_deref().unwrap_or_default().trim();
+                            progress.partial = Some(apology.to_string());
+                            tx.send(ConversationEvent::Retracted {
+                                original,
+                                apology: apology.to_string(),
+                            })
+                            .ok();
+                        }
+                        if !answer.source_attributions.is_empty() {
+                            tx.send(ConversationEvent::Sources(
+                                answer.source_attributions.clone(),
+                            ))
+                            .ok();
+                        }
+                        if !answer.suggested_responses.is_empty() {
+                            tx.send(ConversationEvent::Suggestions(
+                                answer
+                                    .suggested_responses
+                                    .iter()
+                                    .map(|response| response.text.clone())
+                                    .collect(),
+                            ))
+                            .ok();
+                        }
+                    }
+                    progress.is_answered = true;
+                    tx.send(ConversationEvent::Complete).ok();
+                }
+                Frame::Completion(_) => {
+                    trace!("invocation completed");
+                    return TurnEnd::Completed;
+                }
+                Frame::Ping => trace!("ping"),
+                Frame::Close(close) => {
+                    trace!("server closed hub, err: {:?}", close.error);
+                    return TurnEnd::Dropped(Error::ConnectionClosed);
+                }
+                frame => {
+                    warn!("unexpected type_id = <yellow>{}</>", frame.type_id());
+                }
+            }
+        }
     }
+}
 
-    async fn write<V: Into<serde_json::Value>>(&mut self, value: V) -> Result<(), Error> {
-        let mut value = serde_json::to_vec(&value.into())?;
-        value.push(WS_DELIMITER);
-        self.writer
-            .lock()
-            .await
-            .as_mut()
-            .ok_or(Error::NotConnected)?
-            .send(value.into())
-            .await?;
-        Ok(())
+/// Store the new turn counters and notify the receiver
+fn update_throttling(
+    shared: &SharedState,
+    tx: &mpsc::UnboundedSender<ConversationEvent>,
+    new_throttling: Option<Throttling>,
+) {
+    if let Some(new_throttling) = new_throttling {
+        *shared.throttling.lock().unwrap() = Some(new_throttling);
+        tx.send(ConversationEvent::Throttling(new_throttling)).ok();
     }
 }
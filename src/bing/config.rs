@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use tokio_native_tls::TlsConnector;
+
+use super::{Error, ProxyConfig, RegionProfile, UpdateMode};
+
+/// Default endpoint used to create a new conversation
+pub const DEFAULT_CREATE_URL: &str = "https://www.bing.com/turing/conversation/create";
+
+/// Default ChatHub websocket endpoint
+pub const DEFAULT_CHATHUB_URL: &str = "wss://sydney.bing.com/sydney/ChatHub";
+
+/// How TLS certificates of the endpoints are verified
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TlsMode {
+    /// Verify certificates against the system trust store
+    #[default]
+    Verify,
+    /// Accept any certificate, including self-signed ones.
+    /// Only meant for local stand-in servers and relays.
+    AcceptInvalidCerts,
+}
+
+/// Retry policy for transient ChatHub connection failures
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    /// Number of retries after the first attempt, 0 disables retrying
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled after every attempt
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between retries
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+        }
+    }
+}
+
+/// Timeouts of the conversation stages, `None` waits forever
+#[derive(Debug, Clone)]
+pub struct TimeoutConfig {
+    /// Creating a conversation
+    pub create: Option<Duration>,
+    /// Connecting to the ChatHub and completing the protocol handshake
+    pub handshake: Option<Duration>,
+    /// Waiting for the first token of an answer
+    pub first_token: Option<Duration>,
+    /// Waiting for the next update once the answer started streaming
+    pub idle: Option<Duration>,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            create: Some(Duration::from_secs(30)),
+            handshake: Some(Duration::from_secs(15)),
+            first_token: Some(Duration::from_secs(60)),
+            idle: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+/// Configuration of the endpoints a conversation talks to
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// URL of the conversation create endpoint
+    pub create_url: String,
+    /// URL of the ChatHub websocket
+    pub chathub_url: String,
+    /// TLS verification mode for both endpoints
+    pub tls: TlsMode,
+    /// Proxy for both endpoints, `None` connects directly, see `ProxyConfig::from_env`
+    pub proxy: Option<ProxyConfig>,
+    /// Keep the ChatHub connection open between turns
+    /// instead of reconnecting for every message
+    pub persistent: bool,
+    /// Interval of keepalive pings on an idle persistent connection
+    pub keepalive_interval: Duration,
+    /// Retries of the connection setup and of drops before the first token
+    pub retry: RetryConfig,
+    /// Timeouts of the conversation stages
+    pub timeouts: TimeoutConfig,
+    /// Locale, market, location and headers sent to both endpoints
+    pub region: RegionProfile,
+    /// How the text of a streaming answer is reported
+    pub update_mode: UpdateMode,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            create_url: DEFAULT_CREATE_URL.to_string(),
+            chathub_url: DEFAULT_CHATHUB_URL.to_string(),
+            tls: TlsMode::default(),
+            proxy: None,
+            persistent: false,
+            keepalive_interval: Duration::from_secs(15),
+            retry: RetryConfig::default(),
+            timeouts: TimeoutConfig::default(),
+            region: RegionProfile::default(),
+            update_mode: UpdateMode::default(),
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Build an HTTP client for the create endpoint
+    pub(crate) fn http_client(&self) -> Result<reqwest::Client, Error> {
+        let mut builder = reqwest::Client::builder()
+            .danger_accept_invalid_certs(self.tls == TlsMode::AcceptInvalidCerts);
+        // The proxy environment variables are only read by `ProxyConfig::from_env`,
+        // so the create request goes the same way as the websocket
+        builder = match &self.proxy {
+            Some(proxy) => builder.proxy(proxy.reqwest_proxy()?),
+            None => builder.no_proxy(),
+        };
+        Ok(builder.build()?)
+    }
+
+    /// Build a TLS connector for the ChatHub websocket
+    pub(crate) fn ws_connector(&self) -> Result<Option<TlsConnector>, Error> {
+        match self.tls {
+            TlsMode::Verify => Ok(None),
+            TlsMode::AcceptInvalidCerts => {
+                let connector = native_tls::TlsConnector::builder()
+                    .danger_accept_invalid_certs(true)
+                    .build()
+                    .map_err(|e| Error::Tls(e.to_string()))?;
+                Ok(Some(connector.into()))
+            }
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+use super::Error;
+
+/// A single cookie, `domain` is `None` if it applies to every host
+#[derive(Clone, PartialEq, Eq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub domain: Option<String>,
+}
+
+impl Cookie {
+    /// Returns true if the cookie should be sent to the host
+    pub fn matches(&self, host: &str) -> bool {
+        match &self.domain {
+            Some(domain) => {
+                let domain = domain.trim_start_matches('.');
+                host.eq_ignore_ascii_case(domain)
+                    || host
+                        .to_lowercase()
+                        .ends_with(&format!(".{}", domain.to_lowercase()))
+            }
+            None => true,
+        }
+    }
+}
+
+// Keep cookie values out of logs
+impl fmt::Debug for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cookie")
+            .field("name", &self.name)
+            .field("domain", &self.domain)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Entry of a JSON cookie export, as produced by common browser extensions
+#[derive(Deserialize)]
+struct JsonCookie {
+    name: String,
+    value: String,
+    #[serde(default)]
+    domain: Option<String>,
+}
+
+/// A set of cookies sent with the create request
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    /// Detect the format of the text and parse it:
+    /// a JSON export, a Netscape `cookies.txt` file or a raw `Cookie:` header
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let text = text.trim();
+        if text.starts_with('[') {
+            Self::from_json(text)
+        } else if text.lines().any(|line| line.split('\t').count() >= 7) {
+            Self::from_netscape(text)
+        } else {
+            Ok(Self::from_header(text))
+        }
+    }
+
+    /// Parse a raw `Cookie:` header value like `_U=...; SRCHHPGUSR=...`
+    /// A value without any `=` is taken as the `_U` cookie
+    pub fn from_header(header: &str) -> Self {
+        let header = header.trim();
+        let header = header
+            .strip_prefix("Cookie:")
+            .or_else(|| header.strip_prefix("cookie:"))
+            .unwrap_or(header)
+            .trim();
+
+        if !header.is_empty() && !header.contains('=') {
+            return Self::default().with("_U", header);
+        }
+
+        header
+            .split(';')
+            .filter_map(|pair| pair.trim().split_once('='))
+            .fold(Self::default(), |jar, (name, value)| {
+                jar.with(name.trim(), value.trim())
+            })
+    }
+
+    /// Parse a Netscape `cookies.txt` file
+    pub fn from_netscape(text: &str) -> Result<Self, Error> {
+        let mut jar = Self::default();
+        for line in text.lines() {
+            // curl marks HttpOnly cookies with this prefix instead of commenting them out
+            let line = line.strip_prefix("#HttpOnly_").unwrap_or(line).trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() < 7 {
+                return Err(Error::InvalidCookies(format!(
+                    "expected 7 tab separated fields, got {}",
+                    fields.len()
+                )));
+            }
+            jar.insert(Cookie {
+                name: fields[5].to_string(),
+                value: fields[6].to_string(),
+                domain: Some(fields[0].to_string()),
+            });
+        }
+        Ok(jar)
+    }
+
+    /// Parse a JSON export, an array of objects with `name`, `value` and `domain`
+    pub fn from_json(text: &str) -> Result<Self, Error> {
+        let cookies: Vec<JsonCookie> =
+            serde_json::from_str(text).map_err(|e| Error::InvalidCookies(e.to_string()))?;
+        let mut jar = Self::default();
+        for cookie in cookies {
+            jar.insert(Cookie {
+                name: cookie.name,
+                value: cookie.value,
+                domain: cookie.domain,
+            });
+        }
+        Ok(jar)
+    }
+
+    /// Add a cookie that applies to every host
+    pub fn with<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.insert(Cookie {
+            name: name.into(),
+            value: value.into(),
+            domain: None,
+        });
+        self
+    }
+
+    /// Add a cookie, replacing the one with the same name and domain
+    pub fn insert(&mut self, cookie: Cookie) {
+        self.cookies
+            .retain(|c| !(c.name == cookie.name && c.domain == cookie.domain));
+        self.cookies.push(cookie);
+    }
+
+    /// Find a cookie value by name, case-insensitively
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.cookies
+            .iter()
+            .find(|cookie| cookie.name.eq_ignore_ascii_case(name))
+            .map(|cookie| cookie.value.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+    }
+
+    pub fn cookies(&self) -> &[Cookie] {
+        &self.cookies
+    }
+
+    /// Build the `Cookie:` header value for the host,
+    /// `None` if no cookie applies to it
+    pub fn header_for(&self, host: &str) -> Option<String> {
+        let header = self
+            .cookies
+            .iter()
+            .filter(|cookie| cookie.matches(host))
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        Some(header).filter(|header| !header.is_empty())
+    }
+}
+
+impl From<&str> for CookieJar {
+    fn from(header: &str) -> Self {
+        Self::from_header(header)
+    }
+}
+
+impl From<String> for CookieJar {
+    fn from(header: String) -> Self {
+        Self::from_header(&header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NETSCAPE: &str = "# Netscape HTTP Cookie File\n\
+        # https://curl.se/docs/http-cookies.html\n\
+        \n\
+        .bing.com\tTRUE\t/\tTRUE\t0\t_U\tabc\n\
+        #HttpOnly_www.bing.com\tFALSE\t/\tTRUE\t0\tMUID\tdef\n\
+        .example.com\tTRUE\t/\tFALSE\t0\tother\tghi\n";
+
+    const JSON: &str = r#"[
+        {"name": "_U", "value": "abc", "domain": ".bing.com", "path": "/", "httpOnly": true},
+        {"name": "MUID", "value": "def"}
+    ]"#;
+
+    #[test]
+    fn header() {
+        let jar = CookieJar::parse("Cookie: _U=abc; SRCHHPGUSR=def=1; broken").unwrap();
+        assert_eq!(jar.get("_U"), Some("abc"));
+        assert_eq!(jar.get("srchhpgusr"), Some("def=1"));
+        assert_eq!(jar.cookies().len(), 2);
+        assert_eq!(
+            jar.header_for("www.bing.com").as_deref(),
+            Some("_U=abc; SRCHHPGUSR=def=1")
+        );
+    }
+
+    #[test]
+    fn bare_value_is_the_u_cookie() {
+        let jar = CookieJar::parse("  abc\n").unwrap();
+        assert_eq!(jar.get("_U"), Some("abc"));
+        assert_eq!(jar.header_for("www.bing.com").as_deref(), Some("_U=abc"));
+
+        assert!(CookieJar::parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn netscape() {
+        let jar = CookieJar::parse(NETSCAPE).unwrap();
+        assert_eq!(jar, CookieJar::from_netscape(NETSCAPE).unwrap());
+        assert_eq!(jar.cookies().len(), 3);
+        assert_eq!(jar.get("_U"), Some("abc"));
+        // HttpOnly lines aren't comments
+        assert_eq!(jar.get("MUID"), Some("def"));
+        assert_eq!(jar.cookies()[1].domain.as_deref(), Some("www.bing.com"));
+
+        assert!(matches!(
+            CookieJar::from_netscape(".bing.com\tTRUE\t/\t_U\tabc"),
+            Err(Error::InvalidCookies(_))
+        ));
+    }
+
+    #[test]
+    fn json() {
+        let jar = CookieJar::parse(JSON).unwrap();
+        assert_eq!(jar, CookieJar::from_json(JSON).unwrap());
+        assert_eq!(jar.get("_U"), Some("abc"));
+        assert_eq!(jar.cookies()[0].domain.as_deref(), Some(".bing.com"));
+        assert_eq!(jar.cookies()[1].domain, None);
+
+        assert!(matches!(
+            CookieJar::parse("[{\"name\": \"_U\"}]"),
+            Err(Error::InvalidCookies(_))
+        ));
+    }
+
+    #[test]
+    fn header_for_matches_domains() {
+        let jar = CookieJar::parse(NETSCAPE).unwrap();
+        assert_eq!(
+            jar.header_for("www.bing.com").as_deref(),
+            Some("_U=abc; MUID=def")
+        );
+        assert_eq!(jar.header_for("BING.com").as_deref(), Some("_U=abc"));
+        assert_eq!(jar.header_for("sydney.bing.com").as_deref(), Some("_U=abc"));
+        assert_eq!(jar.header_for("notbing.com"), None);
+        assert_eq!(jar.header_for("example.com").as_deref(), Some("other=ghi"));
+
+        // Cookies without a domain go to every host
+        let jar = CookieJar::parse(JSON).unwrap();
+        assert_eq!(jar.header_for("example.com").as_deref(), Some("MUID=def"));
+    }
+}
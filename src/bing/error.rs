@@ -1,25 +1,21 @@
-use thiserror::Error;
+use super::{protocol::ChatResult, Error};
 
-#[derive(Error, Debug)]
-pub enum Error {
-    #[error("cookie \"_U\" not found")]
-    CookieNotFound,
-
-    #[error("not connected")]
-    NotConnected,
-
-    #[error("init error, failed to read first message")]
-    Init,
-
-    #[error("websocket connection is busy")]
-    WsBusy,
-
-    #[error(transparent)]
-    Json(#[from] serde_json::Error),
-
-    #[error(transparent)]
-    Http(#[from] reqwest::Error),
-
-    #[error(transparent)]
-    Ws(#[from] async_tungstenite::tungstenite::Error),
+impl Error {
+    /// Map a result reported by the server to an error,
+    /// `None` if the request succeeded
+    pub fn from_result(result: &ChatResult) -> Option<Self> {
+        let message = result.message.clone().unwrap_or_default();
+        Some(match result.value.as_str() {
+            "Success" => return None,
+            "UnauthorizedRequest" => Self::Unauthorized(message),
+            "Throttled" => Self::Throttled(message),
+            "InvalidSession" => Self::InvalidSession(message),
+            "CaptchaChallenge" => Self::CaptchaChallenge(message),
+            "Forbidden" => Self::Forbidden(message),
+            value => Self::Server {
+                value: value.to_string(),
+                message,
+            },
+        })
+    }
 }
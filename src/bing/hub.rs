@@ -0,0 +1,265 @@
+use std::{cmp, future::Future, time::Duration};
+
+use async_tungstenite::{
+    tokio::ClientStream,
+    tungstenite::{
+        client::IntoClientRequest,
+        http::header::{HeaderName, HeaderValue},
+        Message,
+    },
+    WebSocketStream,
+};
+use futures::{future, SinkExt, StreamExt};
+use serde::Serialize;
+use simplelog::{error, trace, warn};
+use tokio::{net::TcpStream, sync::oneshot, task::JoinHandle, time::Instant};
+
+use super::{
+    protocol::{encode, split_frames, Frame, Handshake, HandshakeResponse},
+    proxy::Io,
+    ClientConfig, Error, RetryConfig,
+};
+
+/// An open ChatHub connection that completed the protocol handshake
+pub(crate) struct Hub {
+    stream: WebSocketStream<ClientStream<Box<dyn Io>>>,
+}
+
+impl Hub {
+    /// Connect to the ChatHub and perform the protocol handshake
+    pub async fn connect(config: &ClientConfig) -> Result<Self, Error> {
+        timeout(
+            config.timeouts.handshake,
+            Error::HandshakeTimeout,
+            Self::handshake(config),
+        )
+        .await
+    }
+
+    async fn handshake(config: &ClientConfig) -> Result<Self, Error> {
+        let mut request = config.chathub_url.as_str().into_client_request()?;
+        for (name, value) in config.region.headers() {
+            request.headers_mut().insert(
+                HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|_| Error::InvalidHeader(name.to_string()))?,
+                HeaderValue::from_str(value).map_err(|_| Error::InvalidHeader(name.to_string()))?,
+            );
+        }
+        let host = request.uri().host().unwrap_or_default().to_string();
+        let port = request
+            .uri()
+            .port_u16()
+            .unwrap_or(match request.uri().scheme_str() {
+                Some("ws") => 80,
+                _ => 443,
+            });
+
+        let socket: Box<dyn Io> = match &config.proxy {
+            Some(proxy) => {
+                trace!("connecting through proxy <yellow>{}</>", proxy);
+                proxy.connect(&host, port).await?
+            }
+            None => Box::new(TcpStream::connect((host.as_str(), port)).await?),
+        };
+        let (stream, _) = async_tungstenite::tokio::client_async_tls_with_connector_and_config(
+            request,
+            socket,
+            config.ws_connector()?,
+            None,
+        )
+        .await?;
+        let mut hub = Self { stream };
+
+        // Init message
+        hub.send(&Handshake::default()).await?;
+
+        // The server answers the handshake with an empty object
+        let response = hub.stream.next().await.ok_or(Error::Init)??.into_text()?;
+        if let Some(response) = split_frames(&response).next() {
+            let response: HandshakeResponse = serde_json::from_str(response)?;
+            if let Some(err) = response.error {
+                error!("handshake failed: <red>{}</>", err);
+                return Err(Error::Init);
+            }
+        }
+        hub.send(&Frame::Ping).await?;
+
+        trace!("ws connected");
+        Ok(hub)
+    }
+
+    /// Connect and send the invocation, retrying transient failures
+    pub async fn open(
+        config: &ClientConfig,
+        invocation: &Frame,
+        backoff: &mut Backoff<'_>,
+    ) -> Result<Self, Error> {
+        loop {
+            let result: Result<Self, Error> = async {
+                let mut hub = Self::connect(config).await?;
+                hub.send(invocation).await?;
+                Ok(hub)
+            }
+            .await;
+
+            match result {
+                Ok(hub) => return Ok(hub),
+                Err(err) if err.is_transient() => match backoff.next() {
+                    Some(delay) => {
+                        warn!("ws connection failed, retrying in {:?}: {}", delay, err);
+                        tokio::time::sleep(delay).await;
+                    }
+                    None => return Err(err),
+                },
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Send a single object to the hub
+    pub async fn send<T: Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.stream.send(encode(value)?.into()).await?;
+        Ok(())
+    }
+
+    /// Read the frames of the next websocket message
+    /// Returns `None` once the connection is closed
+    pub async fn next_frames(&mut self) -> Option<Result<Vec<Frame>, Error>> {
+        loop {
+            let text = match self.stream.next().await? {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Binary(bytes)) => match String::from_utf8(bytes) {
+                    Ok(text) => text,
+                    Err(err) => {
+                        error!("expected utf-8, err: <red>{}</>", err);
+                        continue;
+                    }
+                },
+                Ok(Message::Close(_)) => {
+                    trace!("ws closed");
+                    return None;
+                }
+                Ok(_) => continue,
+                Err(err) => return Some(Err(err.into())),
+            };
+
+            let frames = split_frames(&text)
+                .filter_map(|object| match serde_json::from_str(object) {
+                    Ok(frame) => Some(frame),
+                    Err(err) => {
+                        error!("expected frame, err: <red>{}</>", err);
+                        None
+                    }
+                })
+                .collect();
+            return Some(Ok(frames));
+        }
+    }
+
+    /// Close the connection gracefully
+    pub async fn close(mut self) {
+        trace!("closing ws");
+        self.stream.close(None).await.ok();
+    }
+
+    /// Keep the connection alive in the background until it is resumed
+    pub fn keep_alive(mut self, interval: Duration) -> IdleHub {
+        let (stop, mut stopped) = oneshot::channel();
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval_at(Instant::now() + interval, interval);
+            loop {
+                tokio::select! {
+                    _ = &mut stopped => return Some(self),
+                    _ = ticker.tick() => {
+                        if let Err(err) = self.send(&Frame::Ping).await {
+                            warn!("idle ws keepalive failed: {}", err);
+                            return None;
+                        }
+                    }
+                    frames = self.next_frames() => match frames {
+                        Some(Ok(frames)) => {
+                            for frame in frames {
+                                trace!("idle ws frame type_id = <yellow>{}</>", frame.type_id());
+                            }
+                        }
+                        Some(Err(err)) => {
+                            warn!("idle ws connection lost: {}", err);
+                            return None;
+                        }
+                        None => return None,
+                    }
+                }
+            }
+        });
+        IdleHub { stop, handle }
+    }
+}
+
+/// A hub connection kept open between turns
+pub(crate) struct IdleHub {
+    stop: oneshot::Sender<()>,
+    handle: JoinHandle<Option<Hub>>,
+}
+
+impl IdleHub {
+    /// Stop the keepalive and take the connection back,
+    /// returns `None` if it died in the meantime
+    pub async fn resume(self) -> Option<Hub> {
+        self.stop.send(()).ok();
+        self.handle.await.ok().flatten()
+    }
+}
+
+/// Exponentially growing delays between retries
+pub(crate) struct Backoff<'a> {
+    config: &'a RetryConfig,
+    retries: u32,
+    delay: Duration,
+}
+
+impl<'a> Backoff<'a> {
+    pub fn new(config: &'a RetryConfig) -> Self {
+        Self {
+            config,
+            retries: 0,
+            delay: config.initial_backoff,
+        }
+    }
+
+    /// Delay before the next retry, `None` once retries are exhausted
+    pub fn next(&mut self) -> Option<Duration> {
+        if self.retries >= self.config.max_retries {
+            return None;
+        }
+        self.retries += 1;
+
+        let delay = self.delay;
+        self.delay = cmp::min(self.delay * 2, self.config.max_backoff);
+        Some(delay)
+    }
+}
+
+/// Run the future, failing with `error` if it doesn't finish in time
+pub(crate) async fn timeout<T, F>(
+    duration: Option<Duration>,
+    error: Error,
+    future: F,
+) -> Result<T, Error>
+where
+    F: Future<Output = Result<T, Error>>,
+{
+    match duration {
+        Some(duration) => tokio::time::timeout(duration, future)
+            .await
+            .unwrap_or(Err(error)),
+        None => future.await,
+    }
+}
+
+/// Sleep until the deadline, or forever if there's none
+pub(crate) async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => future::pending().await,
+    }
+}
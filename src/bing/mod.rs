@@ -1,5 +1,28 @@
+pub use crate::backend::{Activity, ConversationEvent, ConversationStyle, Error, UpdateMode};
+
 mod error;
-pub use error::*;
 
 mod client;
+mod hub;
 pub use client::*;
+
+mod config;
+pub use config::*;
+
+mod style;
+
+pub mod protocol;
+
+mod proxy;
+pub use proxy::*;
+
+mod cookie;
+pub use cookie::*;
+
+mod region;
+pub use region::*;
+
+mod activity;
+
+mod pool;
+pub use pool::*;
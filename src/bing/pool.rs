@@ -0,0 +1,188 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use simplelog::{trace, warn};
+
+use super::{ClientConfig, Conversation, CookieJar, Error};
+
+/// Health of an account in the pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountHealth {
+    Healthy,
+    /// The account is skipped for the remaining time
+    CoolingDown(Duration),
+}
+
+/// An account of the pool
+#[derive(Debug, Clone)]
+pub struct Account {
+    pub name: String,
+    pub cookies: CookieJar,
+    cooldown_until: Option<Instant>,
+}
+
+impl Account {
+    pub fn health(&self) -> AccountHealth {
+        match self.cooldown_until {
+            Some(until) if until > Instant::now() => {
+                AccountHealth::CoolingDown(until - Instant::now())
+            }
+            _ => AccountHealth::Healthy,
+        }
+    }
+}
+
+struct PoolState {
+    accounts: Vec<Account>,
+    /// Index of the account to try first next time
+    next: usize,
+}
+
+/// Several accounts conversations are created with in turn,
+/// skipping the ones that are throttled or unauthorized
+#[derive(Clone)]
+pub struct AccountPool {
+    state: Arc<Mutex<PoolState>>,
+    cooldown: Duration,
+}
+
+impl Default for AccountPool {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(15 * 60))
+    }
+}
+
+impl AccountPool {
+    /// Create an empty pool, failing accounts are skipped for `cooldown`
+    pub fn new(cooldown: Duration) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(PoolState {
+                accounts: vec![],
+                next: 0,
+            })),
+            cooldown,
+        }
+    }
+
+    /// Add an account, replacing the one with the same name
+    pub fn add<N: Into<String>, C: Into<CookieJar>>(&self, name: N, cookies: C) {
+        let name = name.into();
+        let mut state = self.state.lock().unwrap();
+        state.accounts.retain(|account| account.name != name);
+        state.accounts.push(Account {
+            name,
+            cookies: cookies.into(),
+            cooldown_until: None,
+        });
+    }
+
+    /// Remove an account, returns false if there's no such account
+    pub fn remove(&self, name: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let len = state.accounts.len();
+        state.accounts.retain(|account| account.name != name);
+        state.accounts.len() != len
+    }
+
+    /// Snapshot of the accounts
+    pub fn accounts(&self) -> Vec<Account> {
+        self.state.lock().unwrap().accounts.clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.lock().unwrap().accounts.is_empty()
+    }
+
+    /// Skip the account until the cooldown is over
+    pub fn mark_cooling_down(&self, name: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(account) = state.accounts.iter_mut().find(|a| a.name == name) {
+            warn!("account <yellow>{}</> is cooling down", name);
+            account.cooldown_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+
+    /// Get a handle to the account, e.g. for a restored conversation
+    pub fn handle(&self, name: &str) -> Option<AccountHandle> {
+        let state = self.state.lock().unwrap();
+        state
+            .accounts
+            .iter()
+            .any(|account| account.name == name)
+            .then(|| AccountHandle {
+                pool: self.clone(),
+                name: name.to_string(),
+            })
+    }
+
+    /// Take the next healthy account, round-robin
+    fn next_healthy(&self, tried: &[String]) -> Option<Account> {
+        let mut state = self.state.lock().unwrap();
+        let len = state.accounts.len();
+        (0..len)
+            .map(|offset| (state.next + offset) % len)
+            .find(|&i| {
+                let account = &state.accounts[i];
+                account.health() == AccountHealth::Healthy && !tried.contains(&account.name)
+            })
+            .map(|i| {
+                state.next = (i + 1) % len;
+                state.accounts[i].clone()
+            })
+    }
+
+    /// Create a conversation with the next healthy account,
+    /// moving on to the next one if an account is rejected
+    pub async fn create_conversation(
+        &self,
+        config: ClientConfig,
+    ) -> Result<(Conversation, AccountHandle), Error> {
+        let mut tried = vec![];
+        let mut last_error = None;
+
+        while let Some(account) = self.next_healthy(&tried) {
+            trace!("creating conversation as <green>{}</>", account.name);
+            match Conversation::with_config(account.cookies, config.clone()).await {
+                Ok(conversation) => {
+                    return Ok((
+                        conversation,
+                        AccountHandle {
+                            pool: self.clone(),
+                            name: account.name,
+                        },
+                    ))
+                }
+                Err(err) if err.is_account_failure() => {
+                    self.mark_cooling_down(&account.name);
+                    tried.push(account.name);
+                    last_error = Some(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(last_error.unwrap_or(Error::NoHealthyAccount))
+    }
+}
+
+/// The account a conversation was created with
+#[derive(Clone)]
+pub struct AccountHandle {
+    pool: AccountPool,
+    name: String,
+}
+
+impl AccountHandle {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Put the account on cooldown if the error means it's throttled or unauthorized
+    pub fn report_error(&self, error: &Error) {
+        if error.is_account_failure() {
+            self.pool.mark_cooling_down(&self.name);
+        }
+    }
+}
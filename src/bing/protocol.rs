@@ -0,0 +1,366 @@
+//! Typed model of the ChatHub SignalR JSON hub protocol.
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+pub use crate::backend::{SourceAttribution, Throttling};
+
+/// Multiple objects in a single WS message are delimited by this character
+pub const WS_DELIMITER: u8 = 0x1e;
+
+/// Split a raw WS text message into the JSON objects it contains
+pub fn split_frames(message: &str) -> impl Iterator<Item = &str> {
+    message
+        .split(WS_DELIMITER as char)
+        .map(|obj| obj.trim())
+        .filter(|obj| !obj.is_empty())
+}
+
+/// Serialize a value and terminate it with the delimiter
+pub fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, serde_json::Error> {
+    let mut bytes = serde_json::to_vec(value)?;
+    bytes.push(WS_DELIMITER);
+    Ok(bytes)
+}
+
+/// The first message sent by the client
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Handshake {
+    pub protocol: String,
+    pub version: u32,
+}
+
+impl Default for Handshake {
+    fn default() -> Self {
+        Self {
+            protocol: "json".to_string(),
+            version: 1,
+        }
+    }
+}
+
+/// The server's answer to the handshake, an empty object on success
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandshakeResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A hub frame, discriminated by its numeric `type` field
+#[derive(Debug, Clone)]
+pub enum Frame {
+    /// Type 1, a partial update of the bot's answer
+    Invocation(Invocation),
+    /// Type 2, the final state of the bot's answer
+    StreamItem(StreamItem),
+    /// Type 3, the invocation is finished
+    Completion(Completion),
+    /// Type 4, a message sent by the client
+    StreamInvocation(StreamInvocation),
+    /// Type 5, the client cancels an invocation
+    CancelInvocation(CancelInvocation),
+    /// Type 6, keepalive
+    Ping,
+    /// Type 7, the server is closing the connection
+    Close(Close),
+}
+
+impl Frame {
+    /// Numeric type of the frame
+    pub fn type_id(&self) -> u8 {
+        match self {
+            Self::Invocation(_) => 1,
+            Self::StreamItem(_) => 2,
+            Self::Completion(_) => 3,
+            Self::StreamInvocation(_) => 4,
+            Self::CancelInvocation(_) => 5,
+            Self::Ping => 6,
+            Self::Close(_) => 7,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Tagged<'a, T> {
+    #[serde(rename = "type")]
+    type_id: u8,
+    #[serde(flatten)]
+    inner: &'a T,
+}
+
+#[derive(Serialize)]
+struct Empty {}
+
+impl Serialize for Frame {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let type_id = self.type_id();
+        match self {
+            Self::Invocation(inner) => Tagged { type_id, inner }.serialize(serializer),
+            Self::StreamItem(inner) => Tagged { type_id, inner }.serialize(serializer),
+            Self::Completion(inner) => Tagged { type_id, inner }.serialize(serializer),
+            Self::StreamInvocation(inner) => Tagged { type_id, inner }.serialize(serializer),
+            Self::CancelInvocation(inner) => Tagged { type_id, inner }.serialize(serializer),
+            Self::Ping => Tagged {
+                type_id,
+                inner: &Empty {},
+            }
+            .serialize(serializer),
+            Self::Close(inner) => Tagged { type_id, inner }.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Frame {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let type_id = value
+            .get("type")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| D::Error::missing_field("type"))?;
+
+        fn inner<T: for<'a> Deserialize<'a>, E: serde::de::Error>(
+            value: serde_json::Value,
+        ) -> Result<T, E> {
+            serde_json::from_value(value).map_err(E::custom)
+        }
+
+        Ok(match type_id {
+            1 => Self::Invocation(inner(value)?),
+            2 => Self::StreamItem(inner(value)?),
+            3 => Self::Completion(inner(value)?),
+            4 => Self::StreamInvocation(inner(value)?),
+            5 => Self::CancelInvocation(inner(value)?),
+            6 => Self::Ping,
+            7 => Self::Close(inner(value)?),
+            id => return Err(D::Error::custom(format!("unknown frame type {}", id))),
+        })
+    }
+}
+
+/// Server to client invocation, `target` is usually `update`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Invocation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invocation_id: Option<String>,
+    pub target: String,
+    #[serde(default)]
+    pub arguments: Vec<Update>,
+}
+
+/// Argument of an `update` invocation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Update {
+    #[serde(default)]
+    pub messages: Vec<BotMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throttling: Option<Throttling>,
+}
+
+/// Final item of a streamed invocation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamItem {
+    #[serde(default)]
+    pub invocation_id: String,
+    #[serde(default)]
+    pub item: ChatResponse,
+}
+
+/// The content of a stream item
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatResponse {
+    #[serde(default)]
+    pub messages: Vec<BotMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_new_message_index: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversation_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversation_expiry_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throttling: Option<Throttling>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<ChatResult>,
+}
+
+impl ChatResponse {
+    /// The bot's answer, the last message written by the bot that is not
+    /// an internal one (search queries, loader messages and so on)
+    pub fn answer(&self) -> Option<&BotMessage> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|message| message.author == "bot" && message.message_type.is_none())
+    }
+}
+
+/// Outcome of a request as reported by the server
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatResult {
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_version: Option<String>,
+}
+
+/// A message in the conversation, written either by the user or the bot
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BotMessage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offense: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_origin: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden_text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spoken_text: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub adaptive_cards: Vec<AdaptiveCard>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub source_attributions: Vec<SourceAttribution>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggested_responses: Vec<SuggestedResponse>,
+}
+
+impl BotMessage {
+    /// Returns true if the message replaces a withdrawn answer
+    pub fn is_retraction(&self) -> bool {
+        self.content_origin.as_deref() == Some("Apology")
+            || !matches!(self.offense.as_deref(), None | Some("None" | "Unknown"))
+    }
+}
+
+/// A rich representation of a bot message
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdaptiveCard {
+    #[serde(default, rename = "type")]
+    pub card_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub body: Vec<AdaptiveCardBlock>,
+}
+
+/// A block inside an adaptive card
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdaptiveCardBlock {
+    #[serde(default, rename = "type")]
+    pub block_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wrap: Option<bool>,
+}
+
+/// A follow-up message suggested by the bot
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuggestedResponse {
+    #[serde(default)]
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_type: Option<String>,
+}
+
+/// The invocation is finished
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Completion {
+    #[serde(default)]
+    pub invocation_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Client to server streamed invocation, `target` is `chat`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamInvocation {
+    pub invocation_id: String,
+    pub target: String,
+    #[serde(default)]
+    pub arguments: Vec<ChatRequest>,
+}
+
+/// The user's message and the conversation it belongs to
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatRequest {
+    pub source: String,
+    pub options_sets: Vec<String>,
+    pub is_start_of_session: bool,
+    pub message: UserMessage,
+    pub conversation_signature: String,
+    pub participant: Participant,
+    pub conversation_id: String,
+}
+
+/// The message written by the user
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserMessage {
+    pub author: String,
+    pub input_method: String,
+    pub text: String,
+    pub message_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Like `lat:47.639557;long:-122.128159;re=1000m;`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// Local time of the user, carrying their time zone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+}
+
+/// Identifies the client in a request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Participant {
+    pub id: String,
+}
+
+/// Cancels a running invocation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelInvocation {
+    pub invocation_id: String,
+}
+
+/// The server closes the connection
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Close {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_reconnect: Option<bool>,
+}
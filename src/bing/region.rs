@@ -0,0 +1,97 @@
+use std::fmt;
+
+use time::{format_description::well_known::Rfc3339, OffsetDateTime, UtcOffset};
+
+/// A point the answers should be relevant to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoLocation {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Accuracy of the point in meters
+    pub radius: u32,
+}
+
+impl fmt::Display for GeoLocation {
+    /// Format as expected by the ChatHub, like `lat:47.639557;long:-122.128159;re=1000m;`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "lat:{:.6};long:{:.6};re={}m;",
+            self.latitude, self.longitude, self.radius
+        )
+    }
+}
+
+/// Where the user is, so answers are relevant to their region
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionProfile {
+    /// Language of the answers, like `en-US`, also sent as `accept-language`
+    pub locale: String,
+    /// Market the search results come from, like `en-US`
+    pub market: String,
+    /// Country code, like `US`
+    pub region: String,
+    /// Sent as `x-forwarded-for`, `None` omits the header
+    pub forwarded_for: Option<String>,
+    /// Time zone of the user, sent with the message timestamp
+    pub utc_offset: Option<UtcOffset>,
+    pub location: Option<GeoLocation>,
+    /// `None` keeps the default user agent
+    pub user_agent: Option<String>,
+    /// Sent with both the create request and the ChatHub handshake
+    pub extra_headers: Vec<(String, String)>,
+}
+
+impl Default for RegionProfile {
+    fn default() -> Self {
+        Self::from_locale("en-US")
+    }
+}
+
+impl RegionProfile {
+    /// Profile with the locale as the market and its country as the region
+    pub fn from_locale(locale: &str) -> Self {
+        let locale = locale.trim();
+        Self {
+            locale: locale.to_string(),
+            market: locale.to_string(),
+            region: locale
+                .rsplit_once('-')
+                .map(|(_, region)| region.to_uppercase())
+                .unwrap_or_default(),
+            // Hacky trick to bypass some errors
+            // Thanks to Reddit :D
+            forwarded_for: Some("1.1.1.1".to_string()),
+            utc_offset: None,
+            location: None,
+            user_agent: None,
+            extra_headers: vec![],
+        }
+    }
+
+    /// Headers sent with both the create request and the ChatHub handshake
+    pub(crate) fn headers(&self) -> Vec<(&str, &str)> {
+        let mut headers = vec![("accept-language", self.locale.as_str())];
+        if let Some(forwarded_for) = &self.forwarded_for {
+            headers.push(("x-forwarded-for", forwarded_for));
+        }
+        if let Some(user_agent) = &self.user_agent {
+            headers.push(("user-agent", user_agent));
+        }
+        headers.extend(
+            self.extra_headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        );
+        headers
+    }
+
+    /// Current time in the user's time zone, `None` if it's unknown
+    pub(crate) fn timestamp(&self) -> Option<String> {
+        let offset = self.utc_offset?;
+        OffsetDateTime::now_utc()
+            .to_offset(offset)
+            .format(&Rfc3339)
+            .ok()
+    }
+}
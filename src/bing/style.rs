@@ -0,0 +1,35 @@
+use super::ConversationStyle;
+
+// Option sets sent with every message, regardless of the style
+const BASE_OPTIONS_SETS: &[&str] = &[
+    "nlu_direct_response_filter",
+    "deepleo",
+    "disable_emoji_spoken_text",
+    "responsible_ai_policy_235",
+    "enablemm",
+    "newspoleansgnd",
+    "cachewriteext",
+    "e2ecachewrite",
+    "dl_edge_prompt",
+    "dv3sugg",
+];
+
+impl ConversationStyle {
+    /// Option sets that enable this style
+    fn style_options_sets(&self) -> &'static [&'static str] {
+        match self {
+            Self::Creative => &["h3imaginative", "clgalileo", "gencontentv3"],
+            Self::Balanced => &["galileo"],
+            Self::Precise => &["h3precise", "clgalileo"],
+        }
+    }
+
+    /// Full list of option sets to send with a message
+    pub fn options_sets(&self) -> Vec<&'static str> {
+        BASE_OPTIONS_SETS
+            .iter()
+            .chain(self.style_options_sets())
+            .copied()
+            .collect()
+    }
+}
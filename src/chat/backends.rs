@@ -0,0 +1,98 @@
+use std::{sync::Arc, time::Duration};
+
+use simplelog::{error, warn};
+
+use super::{BackendKind, Conversation, SavedConversation, Settings, Storage};
+
+use crate::{
+    backend::{self, BingBackend, ChatBackend, EchoBackend, UpdateMode},
+    bing::{AccountPool, CookieJar},
+};
+
+const CONVERSATIONS_KEY: &str = "conversations";
+
+/// Creates the backends conversations are created and restored with.
+#[derive(Default)]
+pub struct Backends {
+    pool: AccountPool,
+    /// Kept for the frontend's lifetime, so conversation ids stay unique.
+    echo: EchoBackend,
+}
+
+impl Backends {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            pool: settings.account_pool(),
+            echo: EchoBackend::new()
+                .with_delay(Duration::from_millis(50))
+                .with_update_mode(UpdateMode::Delta),
+        }
+    }
+
+    pub fn pool(&self) -> &AccountPool {
+        &self.pool
+    }
+
+    pub fn get(
+        &self,
+        settings: &Settings,
+        kind: BackendKind,
+    ) -> Result<Arc<dyn ChatBackend>, backend::Error> {
+        Ok(match kind {
+            // The cookie may be a whole cookies.txt or JSON export, like from `--cookie-file`
+            BackendKind::Bing => Arc::new(BingBackend::new(
+                CookieJar::parse(&settings.cookie)?,
+                self.pool.clone(),
+                settings.client_config()?,
+            )),
+            BackendKind::Echo => Arc::new(self.echo.clone()),
+        })
+    }
+
+    /// True if new conversations of the kind can be created with the settings.
+    pub fn is_ready(&self, settings: &Settings, kind: BackendKind) -> bool {
+        kind != BackendKind::Bing || !settings.cookie.trim().is_empty() || !self.pool.is_empty()
+    }
+
+    /// Reopen the conversations of the previous run, skipping the expired ones.
+    pub fn restore_conversations<S: Storage + ?Sized>(
+        &self,
+        settings: &Settings,
+        storage: &S,
+    ) -> Vec<Conversation> {
+        let saved: Vec<SavedConversation> = storage
+            .get_string(CONVERSATIONS_KEY)
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+
+        let mut conversations = vec![];
+        for saved in saved {
+            let backend = match self.get(settings, saved.backend()) {
+                Ok(backend) => backend,
+                Err(e) => {
+                    error!("failed to restore conversation: {}", e);
+                    continue;
+                }
+            };
+            match Conversation::restore(saved, backend.as_ref()) {
+                Ok(conversation) => conversations.push(conversation),
+                Err(e) => warn!("{} conversation not restored: {}", backend.name(), e),
+            }
+        }
+        conversations
+    }
+}
+
+/// Store the conversations, so the next run of any frontend reopens them.
+pub fn save_conversations<S: Storage + ?Sized>(
+    conversations: &mut [Conversation],
+    storage: &mut S,
+) {
+    let saved: Vec<SavedConversation> = conversations
+        .iter_mut()
+        .filter_map(Conversation::save)
+        .collect();
+    if let Ok(saved) = serde_json::to_string(&saved) {
+        storage.set_string(CONVERSATIONS_KEY, saved);
+    }
+}
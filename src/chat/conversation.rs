@@ -0,0 +1,426 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use simplelog::error;
+
+use super::BackendKind;
+
+use crate::backend::{
+    self, ChatBackend, ChatConversation, ConversationEvent, ConversationStyle, SourceAttribution,
+    Throttling,
+};
+
+/// A conversation as it's stored between runs.
+#[derive(Serialize, Deserialize)]
+pub struct SavedConversation {
+    /// State of the backend's conversation.
+    state: serde_json::Value,
+    messages: Vec<Message>,
+    #[serde(default)]
+    style: ConversationStyle,
+    #[serde(default)]
+    backend: BackendKind,
+}
+
+impl SavedConversation {
+    pub fn backend(&self) -> BackendKind {
+        self.backend
+    }
+}
+
+/// Called when the conversation changed and the frontend should redraw.
+pub type Repaint = Arc<dyn Fn() + Send + Sync>;
+
+// A wrapped conversation, which stores the conversation's messages history.
+pub struct Conversation {
+    /// The conversation's id.
+    id: String,
+    /// The wrapped conversation.
+    backend_conversation: Arc<tokio::sync::Mutex<Box<dyn ChatConversation>>>,
+    /// Order of the messages is from the newest to the oldest.
+    messages: Arc<std::sync::Mutex<Vec<Message>>>,
+    /// Handle to the channel that updates the bot's answer.
+    handle: Option<JoinHandle<()>>,
+    /// Follow-up messages suggested after the last answer.
+    suggestions: Arc<std::sync::Mutex<Vec<String>>>,
+    /// Turn counters reported by the server.
+    throttling: Arc<std::sync::Mutex<Option<Throttling>>>,
+    /// What the bot is doing while the answer is received, like a web search.
+    activity: Arc<std::sync::Mutex<Option<String>>>,
+    /// The style used for the next messages.
+    style: ConversationStyle,
+    /// Extra information about the conversation, like the account it uses.
+    label: Option<String>,
+    /// The last state read from the wrapped conversation.
+    state: Option<serde_json::Value>,
+    /// The backend the conversation was created with.
+    backend: BackendKind,
+}
+
+impl Conversation {
+    pub fn new(backend: BackendKind, backend_conversation: Box<dyn ChatConversation>) -> Self {
+        Self {
+            id: backend_conversation
+                .id()
+                .to_string()
+                .chars()
+                .rev()
+                .take(8)
+                .collect(),
+            messages: Arc::new(Mutex::new(vec![])),
+            handle: None,
+            suggestions: Arc::new(Mutex::new(vec![])),
+            throttling: Arc::new(Mutex::new(backend_conversation.throttling())),
+            activity: Arc::new(Mutex::new(None)),
+            style: backend_conversation.style(),
+            label: backend_conversation.label(),
+            state: backend_conversation.save(),
+            backend_conversation: Arc::new(tokio::sync::Mutex::new(backend_conversation)),
+            backend,
+        }
+    }
+
+    /// Reopen a conversation saved by a previous run.
+    pub fn restore(
+        saved: SavedConversation,
+        backend: &dyn ChatBackend,
+    ) -> Result<Self, backend::Error> {
+        let mut conversation = Self::new(saved.backend, backend.restore_conversation(saved.state)?);
+        *conversation.messages.lock().unwrap() = saved.messages;
+        conversation.style = saved.style;
+        Ok(conversation)
+    }
+
+    /// Get the conversation as it should be stored,
+    /// `None` if the backend can't restore it.
+    pub fn save(&mut self) -> Option<SavedConversation> {
+        // The wrapped conversation is locked while a message is sent, keep the last state then
+        if let Ok(backend_conversation) = self.backend_conversation.try_lock() {
+            self.state = backend_conversation.save();
+        }
+        Some(SavedConversation {
+            state: self.state.clone()?,
+            messages: self.messages.lock().unwrap().clone(),
+            style: self.style,
+            backend: self.backend,
+        })
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn msgs(&mut self) -> &Arc<Mutex<Vec<Message>>> {
+        self.prepare_handle();
+        &self.messages
+    }
+
+    pub fn suggestions(&self) -> &Arc<Mutex<Vec<String>>> {
+        &self.suggestions
+    }
+
+    pub fn throttling(&self) -> Option<Throttling> {
+        *self.throttling.lock().unwrap()
+    }
+
+    pub fn activity(&self) -> Option<String> {
+        self.activity.lock().unwrap().clone()
+    }
+
+    pub fn style(&self) -> ConversationStyle {
+        self.style
+    }
+
+    pub fn style_mut(&mut self) -> &mut ConversationStyle {
+        &mut self.style
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.handle.is_some()
+    }
+
+    pub fn send_user_message<C: Into<String>>(&mut self, repaint: Repaint, content: C) {
+        let content = content.into();
+        self.suggestions.lock().unwrap().clear();
+
+        self.messages.lock().unwrap().push(Message::Text {
+            sender: Sender::User,
+            content: content.clone(),
+            sources: vec![],
+            retracted: None,
+        });
+
+        let messages = self.messages.clone();
+        let suggestions = self.suggestions.clone();
+        let throttling = self.throttling.clone();
+        let activity = self.activity.clone();
+        let backend_conversation = self.backend_conversation.clone();
+        let style = self.style;
+        self.handle = Some(tokio::spawn(async move {
+            // The lock is released once the message is sent, so the answer can be stopped
+            let result = {
+                let mut backend_conversation = backend_conversation.lock().await;
+                let result = backend_conversation.send_message(content, style).await;
+                if let Err(e) = &result {
+                    backend_conversation.report_error(e);
+                }
+                result
+            };
+            let mut channel = match result {
+                Ok(channel) => channel,
+                Err(e) => {
+                    error!("failed to send message: {}", e);
+                    let mut messages = messages.lock().unwrap();
+                    messages.push(Message::Error(e.to_string()));
+                    messages.push(Message::Separator);
+                    repaint();
+                    return;
+                }
+            };
+
+            let mut needs_creation = true;
+            while let Some(event) = channel.recv().await {
+                match event {
+                    ConversationEvent::Update(string) | ConversationEvent::Rewrite(string) => {
+                        if needs_creation {
+                            messages.lock().unwrap().push(Message::Text {
+                                sender: Sender::Bot,
+                                content: string,
+                                sources: vec![],
+                                retracted: None,
+                            });
+                            needs_creation = false;
+                        } else {
+                            for msg in messages.lock().unwrap().iter_mut().rev() {
+                                if let Message::Text {
+                                    sender, content, ..
+                                } = msg
+                                {
+                                    if matches!(sender, Sender::Bot) {
+                                        *content = string + "...";
+                                        break;
+                                    }
+                                }
+                            }
+                        }
+
+                        repaint();
+                    }
+                    ConversationEvent::Delta(delta) => {
+                        let mut messages = messages.lock().unwrap();
+                        match last_bot_content(&mut messages) {
+                            Some(content) if !needs_creation => {
+                                let text = content.strip_suffix("...").unwrap_or(content);
+                                *content = format!("{}{}...", text, delta);
+                            }
+                            _ => {
+                                messages.push(Message::Text {
+                                    sender: Sender::Bot,
+                                    content: delta + "...",
+                                    sources: vec![],
+                                    retracted: None,
+                                });
+                                needs_creation = false;
+                            }
+                        }
+                        repaint();
+                    }
+                    ConversationEvent::Sources(new_sources) => {
+                        for msg in messages.lock().unwrap().iter_mut().rev() {
+                            if let Message::Text {
+                                sender, sources, ..
+                            } = msg
+                            {
+                                if matches!(sender, Sender::Bot) {
+                                    *sources = new_sources;
+                                    break;
+                                }
+                            }
+                        }
+                        repaint();
+                    }
+                    ConversationEvent::Retracted { original, apology } => {
+                        let mut messages = messages.lock().unwrap();
+                        let last_bot_message = messages.iter_mut().rev().find(|msg| {
+                            matches!(
+                                msg,
+                                Message::Text {
+                                    sender: Sender::Bot,
+                                    ..
+                                }
+                            )
+                        });
+                        match last_bot_message {
+                            Some(Message::Text {
+                                content, retracted, ..
+                            }) if !needs_creation => {
+                                *content = apology + "...";
+                                *retracted = Some(original);
+                            }
+                            _ => {
+                                messages.push(Message::Text {
+                                    sender: Sender::Bot,
+                                    content: apology + "...",
+                                    sources: vec![],
+                                    retracted: Some(original),
+                                });
+                                needs_creation = false;
+                            }
+                        }
+                        repaint();
+                    }
+                    ConversationEvent::Suggestions(new_suggestions) => {
+                        *suggestions.lock().unwrap() = new_suggestions;
+                    }
+                    ConversationEvent::Progress(new_activity) => {
+                        *activity.lock().unwrap() = Some(new_activity.to_string());
+                        repaint();
+                    }
+                    ConversationEvent::Throttling(new_throttling) => {
+                        *throttling.lock().unwrap() = Some(new_throttling);
+                        repaint();
+                    }
+                    ConversationEvent::Failed { partial, error } => {
+                        backend_conversation.lock().await.report_error(&error);
+                        let mut messages = messages.lock().unwrap();
+                        if needs_creation {
+                            if !partial.is_empty() {
+                                messages.push(Message::Text {
+                                    sender: Sender::Bot,
+                                    content: partial,
+                                    sources: vec![],
+                                    retracted: None,
+                                });
+                            }
+                        } else {
+                            for msg in messages.iter_mut().rev() {
+                                if let Message::Text {
+                                    sender, content, ..
+                                } = msg
+                                {
+                                    if matches!(sender, Sender::Bot) {
+                                        *content = partial;
+                                        break;
+                                    }
+                                }
+                            }
+                        }
+                        messages.push(Message::Error(error.to_string()));
+                        messages.push(Message::Separator);
+                        repaint();
+                        break;
+                    }
+                    ConversationEvent::Complete | ConversationEvent::Cancelled => {
+                        let mut messages = messages.lock().unwrap();
+                        for msg in messages.iter_mut().rev() {
+                            if let Message::Text {
+                                sender, content, ..
+                            } = msg
+                            {
+                                if matches!(sender, Sender::Bot) {
+                                    if let Some(stripped) = content.strip_suffix("...") {
+                                        *content = stripped.to_string();
+                                    }
+                                    break;
+                                }
+                            }
+                        }
+                        messages.push(Message::Separator);
+                        repaint();
+                        break;
+                    }
+                }
+            }
+
+            *activity.lock().unwrap() = None;
+            repaint();
+        }));
+    }
+
+    /// Send the last user message again.
+    /// Bing can't replace an answer, so the new one is a new turn.
+    /// Returns false if there's no message to send.
+    pub fn regenerate(&mut self, repaint: Repaint) -> bool {
+        let last = self
+            .messages
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find_map(|msg| match msg {
+                Message::Text {
+                    sender: Sender::User,
+                    content,
+                    ..
+                } => Some(content.clone()),
+                _ => None,
+            });
+        match last {
+            Some(content) => {
+                self.send_user_message(repaint, content);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop the answer that is being received.
+    pub fn stop(&self) {
+        let backend_conversation = self.backend_conversation.clone();
+        tokio::spawn(async move {
+            backend_conversation.lock().await.cancel();
+        });
+    }
+
+    fn prepare_handle(&mut self) {
+        if let Some(handle) = &self.handle {
+            if handle.is_finished() {
+                self.handle = None;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Message {
+    Text {
+        sender: Sender,
+        content: String,
+        /// Web sources cited by a bot message, shown as footnotes.
+        sources: Vec<SourceAttribution>,
+        /// The answer shown before the bot replaced it with an apology.
+        #[serde(default)]
+        retracted: Option<String>,
+    },
+    Error(String),
+    Separator,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Sender {
+    User,
+    Bot,
+}
+
+/// Text of a message as shown to the user, with citations like `[^1^]` turned into `[1]`.
+pub fn display_text(content: &str) -> String {
+    content.replace("[^", "[").replace("^]", "]")
+}
+
+/// The text of the newest bot message.
+fn last_bot_content(messages: &mut [Message]) -> Option<&mut String> {
+    messages.iter_mut().rev().find_map(|msg| match msg {
+        Message::Text {
+            sender: Sender::Bot,
+            content,
+            ..
+        } => Some(content),
+        _ => None,
+    })
+}
@@ -0,0 +1,11 @@
+mod backends;
+pub use backends::*;
+
+mod conversation;
+pub use conversation::*;
+
+mod settings;
+pub use settings::*;
+
+mod storage;
+pub use storage::*;
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+
+use super::Storage;
+
+use crate::bing::{self, AccountPool, ClientConfig, ProxyConfig, RegionProfile, UpdateMode};
+
+/// Service new conversations are created with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BackendKind {
+    #[default]
+    Bing,
+    /// Answers with the message itself, for trying the UI offline.
+    Echo,
+}
+
+impl BackendKind {
+    pub const ALL: [Self; 2] = [Self::Bing, Self::Echo];
+}
+
+/// An account of the pool, as it's stored.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AccountEntry {
+    pub name: String,
+    pub cookie: String,
+}
+
+pub struct Settings {
+    pub ui_scale: f32,
+    pub cookie: String,
+    /// Proxy URL, falls back to the environment if empty.
+    pub proxy: String,
+    /// Locale like `de-DE` the answers are tailored to, the default region if empty.
+    pub locale: String,
+    /// Accounts conversations are created with in turn, if any.
+    pub accounts: Vec<AccountEntry>,
+    pub backend: BackendKind,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            ui_scale: 1.0,
+            cookie: String::new(),
+            proxy: String::new(),
+            locale: String::new(),
+            accounts: vec![],
+            backend: BackendKind::default(),
+        }
+    }
+}
+
+const COOKIE_KEY: &str = "cookie";
+const PROXY_KEY: &str = "proxy";
+const LOCALE_KEY: &str = "locale";
+const ACCOUNTS_KEY: &str = "accounts";
+const BACKEND_KEY: &str = "backend";
+
+impl Settings {
+    pub fn new<S: Storage + ?Sized>(storage: &S) -> Self {
+        Self {
+            cookie: storage.get_string(COOKIE_KEY).unwrap_or_default(),
+            proxy: storage.get_string(PROXY_KEY).unwrap_or_default(),
+            locale: storage.get_string(LOCALE_KEY).unwrap_or_default(),
+            accounts: storage
+                .get_string(ACCOUNTS_KEY)
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            backend: storage
+                .get_string(BACKEND_KEY)
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    pub fn save<S: Storage + ?Sized>(&self, storage: &mut S) {
+        storage.set_string(COOKIE_KEY, self.cookie.clone());
+        storage.set_string(PROXY_KEY, self.proxy.clone());
+        storage.set_string(LOCALE_KEY, self.locale.clone());
+        if let Ok(accounts) = serde_json::to_string(&self.accounts) {
+            storage.set_string(ACCOUNTS_KEY, accounts);
+        }
+        if let Ok(backend) = serde_json::to_string(&self.backend) {
+            storage.set_string(BACKEND_KEY, backend);
+        }
+        storage.flush();
+    }
+
+    pub fn client_config(&self) -> Result<ClientConfig, bing::Error> {
+        Ok(ClientConfig {
+            proxy: match self.proxy.trim() {
+                "" => ProxyConfig::from_env(),
+                proxy => Some(proxy.parse()?),
+            },
+            region: match self.locale.trim() {
+                "" => RegionProfile::default(),
+                locale => RegionProfile::from_locale(locale),
+            },
+            update_mode: UpdateMode::Delta,
+            ..Default::default()
+        })
+    }
+
+    pub fn account_pool(&self) -> AccountPool {
+        let pool = AccountPool::default();
+        for account in &self.accounts {
+            pool.add(account.name.clone(), account.cookie.clone());
+        }
+        pool
+    }
+}
@@ -0,0 +1,91 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use simplelog::error;
+
+use crate::APP_NAME;
+
+/// Directory the frontends and the commands keep their data in.
+pub fn data_dir() -> Option<PathBuf> {
+    directories_next::ProjectDirs::from("", "", APP_NAME).map(|dirs| dirs.data_dir().to_path_buf())
+}
+
+/// A key-value store the settings and conversations are saved in.
+pub trait Storage {
+    fn get_string(&self, key: &str) -> Option<String>;
+
+    fn set_string(&mut self, key: &str, value: String);
+
+    /// Write the changes, e.g. to disk.
+    fn flush(&mut self);
+}
+
+#[cfg(feature = "gui")]
+impl Storage for dyn eframe::Storage + '_ {
+    fn get_string(&self, key: &str) -> Option<String> {
+        eframe::Storage::get_string(self, key)
+    }
+
+    fn set_string(&mut self, key: &str, value: String) {
+        eframe::Storage::set_string(self, key, value)
+    }
+
+    fn flush(&mut self) {
+        eframe::Storage::flush(self)
+    }
+}
+
+/// The file eframe persists the GUI's state in,
+/// so the other frontends share the GUI's settings and conversations.
+#[derive(Default)]
+pub struct FileStorage {
+    /// `None` if there's no data directory, nothing is written then.
+    path: Option<PathBuf>,
+    kv: HashMap<String, String>,
+    dirty: bool,
+}
+
+impl FileStorage {
+    pub fn load() -> Self {
+        let path = data_dir().map(|dir| dir.join("app.ron"));
+        Self {
+            kv: path
+                .as_ref()
+                .and_then(|path| std::fs::read_to_string(path).ok())
+                .and_then(|ron| ron::from_str(&ron).ok())
+                .unwrap_or_default(),
+            path,
+            dirty: false,
+        }
+    }
+}
+
+impl Storage for FileStorage {
+    fn get_string(&self, key: &str) -> Option<String> {
+        self.kv.get(key).cloned()
+    }
+
+    fn set_string(&mut self, key: &str, value: String) {
+        if self.kv.get(key) != Some(&value) {
+            self.kv.insert(key.to_string(), value);
+            self.dirty = true;
+        }
+    }
+
+    fn flush(&mut self) {
+        let Some(path) = self.path.as_ref().filter(|_| self.dirty) else {
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .map_err(|e| e.to_string())
+            .and_then(|_| {
+                ron::ser::to_string_pretty(&self.kv, Default::default()).map_err(|e| e.to_string())
+            })
+            .and_then(|ron| std::fs::write(path, ron).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => self.dirty = false,
+            Err(e) => error!("failed to save {}: {}", path.display(), e),
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use anyhow::{anyhow, Context};
+use bing_client::{
+    backend::{
+        AnswerText, ChatBackend, ChatConversation, ConversationEvent, ConversationStyle, Error,
+        SourceAttribution, Throttling,
+    },
+    chat::data_dir,
+};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+
+use super::{bing_backend, CookieArgs};
+
+#[derive(Debug, Args)]
+pub struct AskArgs {
+    /// The message to send, `-` reads it from stdin
+    pub prompt: String,
+    /// Style of the answer, the conversation's style by default
+    #[arg(short, long)]
+    pub style: Option<ConversationStyle>,
+    /// Print the answer as a JSON object once it's complete
+    #[arg(long)]
+    pub json: bool,
+    /// Continue the saved conversation with this name, it's created if it doesn't exist
+    #[arg(short, long, value_name = "NAME")]
+    pub conversation: Option<String>,
+    #[command(flatten)]
+    pub cookies: CookieArgs,
+}
+
+/// Exit codes of `ask`, shown in its help
+pub(super) const EXIT_CODES_HELP: &str = "Exit codes:
+  0  the answer is complete
+  1  any other failure
+  3  the cookies are missing or rejected
+  4  throttled, or the conversation's turn limit is reached
+  5  timed out
+  6  the saved conversation expired, the next run starts a new one";
+
+/// Exit code of a failure, so scripts can tell them apart
+fn exit_code(err: &Error) -> u8 {
+    match err {
+        Error::CookieNotFound
+        | Error::InvalidCookies(_)
+        | Error::Unauthorized(_)
+        | Error::Forbidden(_)
+        | Error::CaptchaChallenge(_)
+        | Error::NoHealthyAccount => 3,
+        Error::Throttled(_) | Error::TurnLimitReached(_) => 4,
+        Error::CreateTimeout
+        | Error::HandshakeTimeout
+        | Error::FirstTokenTimeout
+        | Error::IdleTimeout => 5,
+        Error::ConversationExpired | Error::InvalidSession(_) => 6,
+        Error::Http(err) => match err.status().map(|status| status.as_u16()) {
+            Some(401 | 403) => 3,
+            Some(429) => 4,
+            _ => 1,
+        },
+        _ => 1,
+    }
+}
+
+/// What `ask --json` prints
+#[derive(Serialize)]
+struct AskOutput {
+    text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    sources: Vec<SourceAttribution>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    suggestions: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    throttling: Option<Throttling>,
+}
+
+/// Conversations saved by name with `ask --conversation`
+#[derive(Default, Serialize, Deserialize)]
+struct SavedConversations(HashMap<String, serde_json::Value>);
+
+impl SavedConversations {
+    fn path() -> Option<PathBuf> {
+        data_dir().map(|dir| dir.join("conversations.json"))
+    }
+
+    fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow!("no data directory"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+/// Send the message and print the answer, errors are printed to stderr
+pub async fn ask(args: AskArgs) -> ExitCode {
+    match run(&args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {:#}", err);
+            ExitCode::from(err.downcast_ref::<Error>().map_or(1, exit_code))
+        }
+    }
+}
+
+async fn run(args: &AskArgs) -> anyhow::Result<()> {
+    let prompt = match args.prompt.as_str() {
+        "-" => {
+            let mut prompt = String::new();
+            io::stdin().read_to_string(&mut prompt)?;
+            prompt
+        }
+        prompt => prompt.to_string(),
+    };
+    if prompt.trim().is_empty() {
+        return Err(anyhow!("the message is empty"));
+    }
+
+    let backend = bing_backend(&args.cookies)?;
+    let mut saved = SavedConversations::load();
+    let mut conversation = open_conversation(&backend, &mut saved, args).await?;
+
+    let style = args.style.unwrap_or(conversation.style());
+    let mut events = match conversation.send_message(prompt, style).await {
+        Ok(events) => events,
+        Err(err) => {
+            conversation.report_error(&err);
+            return Err(err.into());
+        }
+    };
+
+    let mut answer = AnswerText::default();
+    let mut output = io::stdout();
+    let mut sources = vec![];
+    let mut suggestions = vec![];
+    let mut throttling = None;
+    let mut failure = None;
+    while let Some(event) = events.recv().await {
+        if let Some(appended) = answer.apply(&event).filter(|_| !args.json) {
+            write!(output, "{}", appended)?;
+            output.flush()?;
+        }
+        match event {
+            ConversationEvent::Sources(new_sources) => sources = new_sources,
+            ConversationEvent::Suggestions(new_suggestions) => suggestions = new_suggestions,
+            ConversationEvent::Throttling(new_throttling) => throttling = Some(new_throttling),
+            ConversationEvent::Failed { error, .. } => {
+                conversation.report_error(&error);
+                failure = Some(error);
+            }
+            _ => {}
+        }
+    }
+    if !args.json && !answer.text().is_empty() {
+        writeln!(output)?;
+    }
+
+    // Saved even if the answer failed, the conversation may still be continued
+    if let Some(name) = &args.conversation {
+        if let Some(state) = conversation.save() {
+            saved.0.insert(name.clone(), state);
+            saved.save()?;
+        }
+    }
+
+    if let Some(error) = failure {
+        return Err(error.into());
+    }
+    if args.json {
+        let output = AskOutput {
+            text: answer.into_text(),
+            sources,
+            suggestions,
+            throttling,
+        };
+        println!("{}", serde_json::to_string(&output)?);
+    }
+    Ok(())
+}
+
+/// Restore the named conversation, or create a new one
+async fn open_conversation(
+    backend: &dyn ChatBackend,
+    saved: &mut SavedConversations,
+    args: &AskArgs,
+) -> anyhow::Result<Box<dyn ChatConversation>> {
+    let Some(name) = &args.conversation else {
+        return Ok(backend.create_conversation().await?);
+    };
+    let Some(state) = saved.0.get(name) else {
+        return Ok(backend.create_conversation().await?);
+    };
+
+    match backend.restore_conversation(state.clone()) {
+        Ok(conversation) => Ok(conversation),
+        Err(err) => {
+            // Forget it, so the next run starts a new conversation with the name
+            saved.0.remove(name);
+            saved.save()?;
+            Err(anyhow::Error::new(err).context(format!("conversation {}", name)))
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use bing_client::{
+    backend::{BingBackend, Error, UpdateMode},
+    bing::CookieJar,
+    chat::{FileStorage, Settings},
+};
+use clap::{Args, Parser, Subcommand};
+
+mod ask;
+pub use ask::*;
+
+#[cfg(feature = "server")]
+mod serve;
+#[cfg(feature = "server")]
+pub use serve::*;
+
+#[cfg(feature = "tui")]
+mod tui;
+#[cfg(feature = "tui")]
+pub use tui::*;
+
+/// An unofficial client for Bing AI, opens the GUI if no command is given
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Send a message and print the answer as it streams
+    #[command(after_help = EXIT_CODES_HELP)]
+    Ask(AskArgs),
+    /// Serve the OpenAI chat completions API on top of Bing conversations
+    #[cfg(feature = "server")]
+    Serve(ServeArgs),
+    /// Open the terminal UI, it shares the settings and conversations with the GUI
+    #[cfg(feature = "tui")]
+    Tui(TuiArgs),
+}
+
+/// Where the cookies come from, shared by the commands
+#[derive(Debug, Args)]
+pub struct CookieArgs {
+    /// File with the cookies, overrides BING_COOKIE and the GUI's cookie and accounts.
+    /// Either a Cookie header, a cookies.txt file or a JSON export
+    #[arg(long, value_name = "PATH")]
+    pub cookie_file: Option<PathBuf>,
+}
+
+impl CookieArgs {
+    /// The cookies from the file or the environment, `None` if neither is given
+    fn explicit_cookie(&self) -> anyhow::Result<Option<String>> {
+        match &self.cookie_file {
+            Some(path) => std::fs::read_to_string(path)
+                .map(Some)
+                .with_context(|| format!("failed to read {}", path.display())),
+            None => Ok(std::env::var("BING_COOKIE")
+                .ok()
+                .filter(|cookie| !cookie.trim().is_empty())),
+        }
+    }
+
+    /// Replace the settings' cookie with the given one, if any.
+    /// The accounts are dropped then, the pool would be used instead of the cookie
+    pub(super) fn apply(&self, settings: &mut Settings) -> anyhow::Result<()> {
+        if let Some(cookie) = self.explicit_cookie()? {
+            settings.cookie = cookie;
+            settings.accounts.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Build the Bing backend from the GUI's settings,
+/// with the cookies from the file or the environment if given
+pub(super) fn bing_backend(args: &CookieArgs) -> anyhow::Result<BingBackend> {
+    let mut settings = Settings::new(&FileStorage::load());
+    args.apply(&mut settings)?;
+
+    let pool = settings.account_pool();
+    if settings.cookie.trim().is_empty() && pool.is_empty() {
+        return Err(anyhow::Error::new(Error::CookieNotFound)
+            .context("set BING_COOKIE, pass --cookie-file or add cookies in the GUI"));
+    }
+    let mut config = settings.client_config()?;
+    config.update_mode = UpdateMode::Delta;
+    Ok(BingBackend::new(
+        CookieJar::parse(&settings.cookie)?,
+        pool,
+        config,
+    ))
+}
@@ -0,0 +1,27 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::anyhow;
+use bing_client::server;
+use clap::Args;
+
+use super::{bing_backend, CookieArgs};
+
+/// Address the server listens on if none is given
+const DEFAULT_SERVE_ADDR: &str = "127.0.0.1:8080";
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(default_value = DEFAULT_SERVE_ADDR)]
+    pub addr: SocketAddr,
+    #[command(flatten)]
+    pub cookies: CookieArgs,
+}
+
+/// Run the OpenAI-compatible server until it fails
+pub async fn serve(args: ServeArgs) -> anyhow::Result<()> {
+    let backend = bing_backend(&args.cookies)?;
+    server::serve(args.addr, Arc::new(backend))
+        .await
+        .map_err(|e| anyhow!("server failed: {}", e))
+}
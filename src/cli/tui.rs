@@ -0,0 +1,21 @@
+use bing_client::{
+    chat::{FileStorage, Settings},
+    tui,
+};
+use clap::Args;
+
+use super::CookieArgs;
+
+#[derive(Debug, Args)]
+pub struct TuiArgs {
+    #[command(flatten)]
+    pub cookies: CookieArgs,
+}
+
+/// Run the terminal UI until it's closed
+pub async fn run_tui(args: TuiArgs) -> anyhow::Result<()> {
+    let storage = FileStorage::load();
+    let mut settings = Settings::new(&storage);
+    args.cookies.apply(&mut settings)?;
+    tui::run(settings, storage).await
+}
@@ -0,0 +1,22 @@
+//! An unofficial client for Bing AI.
+//!
+//! The `bing` module is the protocol client and `backend` abstracts over it,
+//! the frontends are behind the `gui`, `tui` and `server` features.
+
+pub mod backend;
+pub mod bing;
+
+#[cfg(feature = "chat")]
+pub mod chat;
+
+#[cfg(feature = "server")]
+pub mod server;
+
+#[cfg(feature = "tui")]
+pub mod tui;
+
+#[cfg(feature = "gui")]
+pub mod ui;
+
+/// Name of the window, also used for the data directory
+pub const APP_NAME: &str = "Bing Client";
@@ -1,30 +1,77 @@
-use anyhow::anyhow;
-use eframe::{NativeOptions, Theme};
+use std::process::ExitCode;
+
+use clap::Parser;
+use cli::{Cli, Command};
 use log::LevelFilter;
 use simplelog::{ColorChoice, ConfigBuilder, TermLogger, TerminalMode};
-use ui::Application;
 
-mod bing;
-mod ui;
+mod cli;
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    TermLogger::init(
-        LevelFilter::Trace,
-        ConfigBuilder::new().add_filter_allow_str("bing").build(),
-        TerminalMode::Mixed,
-        ColorChoice::Auto,
-    )
-    .expect("failed to init logger");
+async fn main() -> anyhow::Result<ExitCode> {
+    let cli = Cli::parse();
+
+    let config = ConfigBuilder::new().add_filter_allow_str("bing").build();
+    match cli.command {
+        // The terminal is drawn on, so the log goes to a file
+        #[cfg(feature = "tui")]
+        Some(Command::Tui(_)) => {
+            if let Some(file) = bing_client::tui::log_file() {
+                simplelog::WriteLogger::init(LevelFilter::Info, config, file)
+                    .expect("failed to init logger");
+            }
+        }
+        // The answer goes to stdout, so only warnings are logged, to stderr
+        Some(Command::Ask(_)) => {
+            TermLogger::init(
+                LevelFilter::Warn,
+                config,
+                TerminalMode::Stderr,
+                ColorChoice::Auto,
+            )
+            .expect("failed to init logger");
+        }
+        _ => {
+            TermLogger::init(
+                LevelFilter::Trace,
+                config,
+                TerminalMode::Mixed,
+                ColorChoice::Auto,
+            )
+            .expect("failed to init logger");
+        }
+    }
+
+    match cli.command {
+        Some(Command::Ask(args)) => return Ok(cli::ask(args).await),
+        #[cfg(feature = "server")]
+        Some(Command::Serve(args)) => cli::serve(args).await?,
+        #[cfg(feature = "tui")]
+        Some(Command::Tui(args)) => cli::run_tui(args).await?,
+        None => run_gui()?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
 
+#[cfg(feature = "gui")]
+fn run_gui() -> anyhow::Result<()> {
     eframe::run_native(
-        "Bing Client",
-        NativeOptions {
+        bing_client::APP_NAME,
+        eframe::NativeOptions {
             follow_system_theme: true,
             // default_theme: Theme::Light,
             ..Default::default()
         },
-        Box::new(|cc| Box::new(Application::new(cc))),
+        Box::new(|cc| Box::new(bing_client::ui::Application::new(cc))),
     )
-    .map_err(|e| anyhow!("failed to run eframe: {}", e))
+    .map_err(|e| anyhow::anyhow!("failed to run eframe: {}", e))
+}
+
+/// Built without the GUI, the commands are all there is
+#[cfg(not(feature = "gui"))]
+fn run_gui() -> anyhow::Result<()> {
+    use clap::CommandFactory;
+
+    Cli::command().print_help()?;
+    Ok(())
 }
@@ -0,0 +1,499 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use hyper::{
+    header,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, StatusCode,
+};
+use serde::Serialize;
+use simplelog::{info, trace, warn};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::backend::{
+    AnswerText, ChatBackend, ChatConversation, ConversationEvent, Error, EventStream,
+};
+
+mod openai;
+use openai::*;
+
+/// Header naming the session, requests of a session continue the same conversation
+const SESSION_HEADER: &str = "x-session-id";
+
+/// Sessions unused for this long are forgotten
+const SESSION_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Most sessions kept, the least recently used ones are forgotten first
+const MAX_SESSIONS: usize = 1000;
+
+type SharedConversation = Arc<tokio::sync::Mutex<Box<dyn ChatConversation>>>;
+
+/// Conversations by session key
+#[derive(Default)]
+struct Sessions(HashMap<String, Session>);
+
+struct Session {
+    conversation: SharedConversation,
+    last_used: Instant,
+}
+
+impl Sessions {
+    fn get(&mut self, key: &str) -> Option<SharedConversation> {
+        self.evict();
+        let session = self.0.get_mut(key)?;
+        session.last_used = Instant::now();
+        Some(session.conversation.clone())
+    }
+
+    fn insert(&mut self, key: String, conversation: SharedConversation) {
+        self.evict();
+        if self.0.len() >= MAX_SESSIONS {
+            if let Some(oldest) = self
+                .0
+                .iter()
+                .min_by_key(|(_, session)| session.last_used)
+                .map(|(key, _)| key.clone())
+            {
+                self.0.remove(&oldest);
+            }
+        }
+        self.0.insert(
+            key,
+            Session {
+                conversation,
+                last_used: Instant::now(),
+            },
+        );
+    }
+
+    /// Forget the conversation of the session, if it's still the given one
+    fn remove(&mut self, key: &str, conversation: &SharedConversation) {
+        if self
+            .0
+            .get(key)
+            .is_some_and(|session| Arc::ptr_eq(&session.conversation, conversation))
+        {
+            trace!("session <green>{}</> ended", key);
+            self.0.remove(key);
+        }
+    }
+
+    fn evict(&mut self) {
+        self.0
+            .retain(|_, session| session.last_used.elapsed() < SESSION_TTL);
+    }
+}
+
+/// A session's conversation, forgotten once it can't be continued
+#[derive(Clone)]
+struct SessionHandle {
+    sessions: Arc<Mutex<Sessions>>,
+    key: Option<String>,
+    conversation: SharedConversation,
+}
+
+impl SessionHandle {
+    /// Forget the session if the error means its conversation can't be continued,
+    /// the next request of the session starts a new one
+    fn check(&self, err: &Error) {
+        if ends_session(err) {
+            self.end();
+        }
+    }
+
+    fn end(&self) {
+        if let Some(key) = &self.key {
+            self.sessions
+                .lock()
+                .unwrap()
+                .remove(key, &self.conversation);
+        }
+    }
+
+    /// Report the failure of an answer to the conversation and the session
+    async fn failed(&self, err: &Error) {
+        self.conversation.lock().await.report_error(err);
+        self.check(err);
+    }
+
+    /// Forget the session once the conversation used up its turns
+    async fn completed(&self) {
+        if self
+            .conversation
+            .lock()
+            .await
+            .throttling()
+            .is_some_and(|throttling| throttling.is_exhausted())
+        {
+            self.end();
+        }
+    }
+}
+
+/// Returns true if the conversation can't take more messages
+fn ends_session(err: &Error) -> bool {
+    matches!(
+        err,
+        Error::ConversationExpired | Error::InvalidSession(_) | Error::TurnLimitReached(_)
+    )
+}
+
+/// Serve the OpenAI chat completions API on top of the backend until the server fails
+pub async fn serve(addr: SocketAddr, backend: Arc<dyn ChatBackend>) -> Result<(), hyper::Error> {
+    let api = Arc::new(Api {
+        backend,
+        sessions: Arc::default(),
+    });
+    let make_service = make_service_fn(move |_| {
+        let api = api.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let api = api.clone();
+                async move { Ok::<_, Infallible>(api.handle(request).await) }
+            }))
+        }
+    });
+
+    let server = hyper::Server::try_bind(&addr)?.serve(make_service);
+    info!("listening on <green>http://{}</>", server.local_addr());
+    server.await
+}
+
+/// Why a request failed
+enum ApiError {
+    BadRequest(String),
+    Backend(Error),
+}
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        Self::Backend(err)
+    }
+}
+
+impl ApiError {
+    fn response(&self) -> Response<Body> {
+        match self {
+            Self::BadRequest(message) => error_response(StatusCode::BAD_REQUEST, message),
+            Self::Backend(err) => error_response(status(err), &err.to_string()),
+        }
+    }
+}
+
+struct Api {
+    backend: Arc<dyn ChatBackend>,
+    sessions: Arc<Mutex<Sessions>>,
+}
+
+impl Api {
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        trace!("<blue>{}</> {}", request.method(), request.uri().path());
+        match (request.method(), request.uri().path()) {
+            (&Method::GET, "/v1/models") => json_response(StatusCode::OK, &models()),
+            (&Method::POST, "/v1/chat/completions") => self
+                .chat_completions(request)
+                .await
+                .unwrap_or_else(|err| err.response()),
+            (_, "/v1/models" | "/v1/chat/completions") => {
+                error_response(StatusCode::METHOD_NOT_ALLOWED, "method not allowed")
+            }
+            (_, path) => error_response(StatusCode::NOT_FOUND, &format!("no route for {}", path)),
+        }
+    }
+
+    async fn chat_completions(&self, request: Request<Body>) -> Result<Response<Body>, ApiError> {
+        let session = request
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+        let body = hyper::body::to_bytes(request.into_body())
+            .await
+            .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+        let request: ChatCompletionRequest =
+            serde_json::from_slice(&body).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+        if !request
+            .messages
+            .iter()
+            .any(|message| message.role == "user")
+        {
+            return Err(ApiError::BadRequest("no user message".to_string()));
+        }
+
+        let (handle, events) = loop {
+            let (conversation, is_new) = self.conversation(session.as_deref()).await?;
+            let handle = SessionHandle {
+                sessions: self.sessions.clone(),
+                key: session.clone(),
+                conversation,
+            };
+            let prompt = prompt(&request.messages, is_new).unwrap_or_default();
+            let result = {
+                let mut conversation = handle.conversation.lock().await;
+                conversation
+                    .send_message(prompt, model_style(&request.model))
+                    .await
+            };
+            match result {
+                Ok(events) => break (handle, events),
+                Err(err) => {
+                    handle.failed(&err).await;
+                    // A new conversation gets the whole transcript, so the session goes on
+                    if !is_new && ends_session(&err) {
+                        continue;
+                    }
+                    return Err(err.into());
+                }
+            }
+        };
+
+        let completion = Completion {
+            id: format!("chatcmpl-{}", Uuid::new_v4().simple()),
+            created: OffsetDateTime::now_utc().unix_timestamp(),
+            model: request.model,
+        };
+        if request.stream {
+            Ok(completion.stream(handle, events))
+        } else {
+            completion.collect(handle, events).await
+        }
+    }
+
+    /// Get the conversation of the session, or create one
+    /// Returns true if the conversation is new
+    async fn conversation(
+        &self,
+        session: Option<&str>,
+    ) -> Result<(SharedConversation, bool), Error> {
+        if let Some(conversation) =
+            session.and_then(|session| self.sessions.lock().unwrap().get(session))
+        {
+            return Ok((conversation, false));
+        }
+
+        let conversation = Arc::new(tokio::sync::Mutex::new(
+            self.backend.create_conversation().await?,
+        ));
+        if let Some(session) = session {
+            trace!("new conversation for session <green>{}</>", session);
+            self.sessions
+                .lock()
+                .unwrap()
+                .insert(session.to_string(), conversation.clone());
+        }
+        Ok((conversation, true))
+    }
+}
+
+/// The answer to a chat completion request
+struct Completion {
+    id: String,
+    created: i64,
+    model: String,
+}
+
+impl Completion {
+    /// Wait for the whole answer
+    async fn collect(
+        self,
+        session: SessionHandle,
+        mut events: EventStream,
+    ) -> Result<Response<Body>, ApiError> {
+        let mut answer = AnswerText::default();
+        while let Some(event) = events.recv().await {
+            if let ConversationEvent::Failed { error, .. } = event {
+                session.failed(&error).await;
+                return Err(error.into());
+            }
+            answer.apply(&event);
+        }
+        session.completed().await;
+
+        Ok(json_response(
+            StatusCode::OK,
+            &ChatCompletion {
+                id: self.id,
+                object: "chat.completion",
+                created: self.created,
+                model: self.model,
+                choices: vec![Choice {
+                    index: 0,
+                    message: ChatMessage {
+                        role: "assistant".to_string(),
+                        content: answer.into_text(),
+                    },
+                    finish_reason: "stop",
+                }],
+                usage: Usage::default(),
+            },
+        ))
+    }
+
+    /// Forward the answer as server-sent events while it's received
+    fn stream(self, session: SessionHandle, mut events: EventStream) -> Response<Body> {
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            let mut answer = AnswerText::default();
+            let mut delta = Some(Delta {
+                role: Some("assistant"),
+                content: None,
+            });
+            loop {
+                let data = match delta.take() {
+                    Some(delta) => sse_data(&self.chunk(delta, None)),
+                    None => match events.recv().await {
+                        Some(ConversationEvent::Failed { error, .. }) => {
+                            session.failed(&error).await;
+                            let data = sse_data(&ErrorResponse {
+                                error: error_body(status(&error), &error.to_string()),
+                            });
+                            sender.send_data(data.into()).await.ok();
+                            return;
+                        }
+                        Some(event) => {
+                            delta = answer.apply(&event).map(|content| Delta {
+                                role: None,
+                                content: Some(content),
+                            });
+                            continue;
+                        }
+                        None => break,
+                    },
+                };
+                // Dropping the events when the client is gone also stops the answer
+                if sender.send_data(data.into()).await.is_err() {
+                    trace!("client disconnected");
+                    return;
+                }
+            }
+
+            session.completed().await;
+            let data = sse_data(&self.chunk(Delta::default(), Some("stop")));
+            if sender.send_data(data.into()).await.is_ok() {
+                sender.send_data("data: [DONE]\n\n".into()).await.ok();
+            }
+        });
+
+        Response::builder()
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .body(body)
+            .unwrap()
+    }
+
+    fn chunk(&self, delta: Delta, finish_reason: Option<&'static str>) -> ChatCompletionChunk {
+        ChatCompletionChunk {
+            id: self.id.clone(),
+            object: "chat.completion.chunk",
+            created: self.created,
+            model: self.model.clone(),
+            choices: vec![ChunkChoice {
+                index: 0,
+                delta,
+                finish_reason,
+            }],
+        }
+    }
+}
+
+/// Text sent for the request: the last user message if the conversation
+/// already knows the history, the whole transcript if it's new
+fn prompt(messages: &[ChatMessage], is_new: bool) -> Option<String> {
+    let last = messages
+        .iter()
+        .rposition(|message| message.role == "user")?;
+    if !is_new || last == 0 {
+        return Some(messages[last].content.clone());
+    }
+    Some(
+        messages[..=last]
+            .iter()
+            .filter(|message| !message.content.is_empty())
+            .map(|message| format!("[{}]: {}", message.role, message.content))
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+    )
+}
+
+fn models() -> ModelList {
+    ModelList {
+        object: "list",
+        data: MODELS
+            .iter()
+            .map(|(id, _)| Model {
+                id,
+                object: "model",
+                owned_by: "bing",
+            })
+            .collect(),
+    }
+}
+
+/// HTTP status of a backend error
+fn status(err: &Error) -> StatusCode {
+    match err {
+        Error::CookieNotFound
+        | Error::InvalidCookies(_)
+        | Error::Unauthorized(_)
+        | Error::CaptchaChallenge(_) => StatusCode::UNAUTHORIZED,
+        Error::Forbidden(_) => StatusCode::FORBIDDEN,
+        Error::Throttled(_) | Error::TurnLimitReached(_) => StatusCode::TOO_MANY_REQUESTS,
+        Error::WsBusy => StatusCode::CONFLICT,
+        Error::ConversationExpired | Error::InvalidSession(_) => StatusCode::GONE,
+        Error::NoHealthyAccount => StatusCode::SERVICE_UNAVAILABLE,
+        Error::CreateTimeout
+        | Error::HandshakeTimeout
+        | Error::FirstTokenTimeout
+        | Error::IdleTimeout => StatusCode::GATEWAY_TIMEOUT,
+        Error::Http(err) => err
+            .status()
+            .filter(StatusCode::is_client_error)
+            .unwrap_or(StatusCode::BAD_GATEWAY),
+        _ => StatusCode::BAD_GATEWAY,
+    }
+}
+
+fn error_body(status: StatusCode, message: &str) -> ErrorBody {
+    ErrorBody {
+        message: message.to_string(),
+        error_type: match status {
+            StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED => {
+                "invalid_request_error"
+            }
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => "authentication_error",
+            StatusCode::TOO_MANY_REQUESTS => "rate_limit_error",
+            _ => "server_error",
+        },
+    }
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    warn!("request failed with {}: {}", status, message);
+    json_response(
+        status,
+        &ErrorResponse {
+            error: error_body(status, message),
+        },
+    )
+}
+
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_vec(body).unwrap_or_default().into())
+        .unwrap()
+}
+
+fn sse_data<T: Serialize>(body: &T) -> String {
+    format!(
+        "data: {}\n\n",
+        serde_json::to_string(body).unwrap_or_default()
+    )
+}
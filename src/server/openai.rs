@@ -0,0 +1,169 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::backend::ConversationStyle;
+
+/// Models listed by `/v1/models`, one for every conversation style
+pub const MODELS: [(&str, ConversationStyle); 3] = [
+    ("bing-creative", ConversationStyle::Creative),
+    ("bing-balanced", ConversationStyle::Balanced),
+    ("bing-precise", ConversationStyle::Precise),
+];
+
+/// Style of the model, the default style for unknown models
+pub fn model_style(model: &str) -> ConversationStyle {
+    MODELS
+        .iter()
+        .find(|(name, _)| *name == model)
+        .map(|(_, style)| *style)
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionRequest {
+    #[serde(default)]
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub stream: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    #[serde(default, deserialize_with = "content_text")]
+    pub content: String,
+}
+
+/// Content of a request message, either plain text or a list of parts
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Content {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Deserialize)]
+struct ContentPart {
+    #[serde(default, rename = "type")]
+    part_type: String,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+/// Read the content as text, joining the text parts and skipping the others like images.
+/// Assistant messages with tool calls have a null content
+fn content_text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(match Option::<Content>::deserialize(deserializer)? {
+        None => String::new(),
+        Some(Content::Text(text)) => text,
+        Some(Content::Parts(parts)) => parts
+            .into_iter()
+            .filter(|part| part.part_type == "text")
+            .filter_map(|part| part.text)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChatCompletion {
+    pub id: String,
+    pub object: &'static str,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<Choice>,
+    pub usage: Usage,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Choice {
+    pub index: u32,
+    pub message: ChatMessage,
+    pub finish_reason: &'static str,
+}
+
+/// Token counts, always zero as Bing doesn't report them
+#[derive(Debug, Default, Serialize)]
+pub struct Usage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChatCompletionChunk {
+    pub id: String,
+    pub object: &'static str,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<ChunkChoice>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChunkChoice {
+    pub index: u32,
+    pub delta: Delta,
+    pub finish_reason: Option<&'static str>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Delta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelList {
+    pub object: &'static str,
+    pub data: Vec<Model>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Model {
+    pub id: &'static str,
+    pub object: &'static str,
+    pub owned_by: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: ErrorBody,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub message: String,
+    #[serde(rename = "type")]
+    pub error_type: &'static str,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content(json: &str) -> String {
+        serde_json::from_str::<ChatMessage>(json).unwrap().content
+    }
+
+    #[test]
+    fn content_forms() {
+        assert_eq!(content(r#"{"role": "user", "content": "Hi"}"#), "Hi");
+        assert_eq!(
+            content(
+                r#"{"role": "user", "content": [
+                    {"type": "text", "text": "Hi"},
+                    {"type": "image_url", "image_url": {"url": "https://example.com/a.png"}},
+                    {"type": "text", "text": "there"}
+                ]}"#
+            ),
+            "Hi\nthere"
+        );
+        assert_eq!(
+            content(r#"{"role": "assistant", "content": null, "tool_calls": []}"#),
+            ""
+        );
+        assert_eq!(content(r#"{"role": "assistant"}"#), "");
+    }
+}
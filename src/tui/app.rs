@@ -0,0 +1,454 @@
+use std::{sync::Arc, time::Duration};
+
+use crossterm::event::{Event, EventStream, KeyCode, KeyEventKind, KeyModifiers};
+use futures::{FutureExt, StreamExt};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Tabs, Wrap},
+    DefaultTerminal, Frame,
+};
+use simplelog::error;
+use tokio::{sync::mpsc, task::JoinHandle};
+use tui_textarea::TextArea;
+
+use crate::{
+    backend::{self, ConversationStyle},
+    chat::{
+        display_text, save_conversations, BackendKind, Backends, Conversation, FileStorage,
+        Message, Repaint, Sender, Settings, Storage,
+    },
+};
+
+/// How often the conversations are saved, so a crash loses little.
+const AUTO_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Lines of the input box, including its border.
+const INPUT_HEIGHT: u16 = 6;
+
+/// Lines scrolled by Page Up and Page Down.
+const SCROLL_STEP: u16 = 10;
+
+const HELP: &str = "Enter send · Alt+Enter new line · Ctrl+N new · Ctrl+W close · Tab next · \
+    Ctrl+T style · Ctrl+B backend · Ctrl+S stop · Ctrl+R regenerate · Alt+1-9 suggestion · \
+    PgUp/PgDn scroll · Esc quit";
+
+/// The terminal counterpart of the GUI's application.
+pub struct Application {
+    settings: Settings,
+    storage: FileStorage,
+    backends: Backends,
+    conversations: Vec<Conversation>,
+    selected_conversation: usize,
+    add_conversation_handle: Option<JoinHandle<Result<Conversation, backend::Error>>>,
+    input: TextArea<'static>,
+    /// Lines scrolled up from the newest message.
+    scroll: u16,
+    /// The last error, shown instead of the help until the next key.
+    status: Option<String>,
+    /// Wakes the event loop up when a conversation changed.
+    repaint: Repaint,
+    should_quit: bool,
+}
+
+impl Application {
+    pub fn new(settings: Settings, storage: FileStorage) -> Self {
+        let backends = Backends::new(&settings);
+        let conversations = backends.restore_conversations(&settings, &storage);
+        Self {
+            settings,
+            storage,
+            backends,
+            conversations,
+            selected_conversation: 0,
+            add_conversation_handle: None,
+            input: new_input(),
+            scroll: 0,
+            status: None,
+            repaint: Arc::new(|| {}),
+            should_quit: false,
+        }
+    }
+
+    /// Handle the keys and redraw until the user quits.
+    pub async fn run(mut self, terminal: &mut DefaultTerminal) -> anyhow::Result<()> {
+        let (repaint_sender, mut repaint_receiver) = mpsc::unbounded_channel();
+        self.repaint = Arc::new(move || {
+            repaint_sender.send(()).ok();
+        });
+        let mut events = EventStream::new();
+        let mut auto_save = tokio::time::interval(AUTO_SAVE_INTERVAL);
+
+        while !self.should_quit {
+            self.prepare_handles();
+            terminal.draw(|frame| self.draw(frame))?;
+
+            tokio::select! {
+                event = events.next() => match event {
+                    Some(event) => self.handle_event(event?),
+                    None => break,
+                },
+                _ = repaint_receiver.recv() => {
+                    // Every update of the answer asks for a repaint, one draw is enough
+                    while repaint_receiver.try_recv().is_ok() {}
+                }
+                _ = auto_save.tick() => self.save(),
+            }
+        }
+
+        self.save();
+        Ok(())
+    }
+
+    fn save(&mut self) {
+        save_conversations(&mut self.conversations, &mut self.storage);
+        self.storage.flush();
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        let Event::Key(key) = event else {
+            return;
+        };
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        self.status = None;
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Esc => self.should_quit = true,
+            KeyCode::Char('c') if ctrl => self.should_quit = true,
+            KeyCode::Char('n') if ctrl => self.add_conversation(),
+            KeyCode::Char('w') if ctrl => self.close_conversation(),
+            KeyCode::Tab => self.select(1),
+            KeyCode::BackTab => self.select(self.conversations.len().saturating_sub(1)),
+            KeyCode::Char('t') if ctrl => {
+                if let Some(conversation) = self.conversations.get_mut(self.selected_conversation) {
+                    let style = conversation.style_mut();
+                    *style = next(&ConversationStyle::ALL, *style);
+                }
+            }
+            KeyCode::Char('b') if ctrl => {
+                self.settings.backend = next(&BackendKind::ALL, self.settings.backend);
+            }
+            KeyCode::Char('s') if ctrl => {
+                if let Some(conversation) = self
+                    .conversations
+                    .get(self.selected_conversation)
+                    .filter(|c| c.is_busy())
+                {
+                    conversation.stop();
+                }
+            }
+            KeyCode::Char('r') if ctrl => self.regenerate(),
+            KeyCode::Char(digit @ '1'..='9') if alt => {
+                let i = digit as usize - '1' as usize;
+                let suggestion = self
+                    .conversations
+                    .get(self.selected_conversation)
+                    .and_then(|c| c.suggestions().lock().unwrap().get(i).cloned());
+                if let Some(suggestion) = suggestion {
+                    self.send(suggestion);
+                }
+            }
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_add(SCROLL_STEP),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(SCROLL_STEP),
+            KeyCode::Enter if alt || key.modifiers.contains(KeyModifiers::SHIFT) => {
+                self.input.insert_newline();
+            }
+            KeyCode::Enter => {
+                let text = self.input.lines().join("\n");
+                if !text.trim().is_empty() && self.send(text) {
+                    self.input = new_input();
+                }
+            }
+            _ => {
+                self.input.input(key);
+            }
+        }
+    }
+
+    /// Select the conversation `offset` tabs to the right, wrapping around.
+    fn select(&mut self, offset: usize) {
+        if !self.conversations.is_empty() {
+            self.selected_conversation =
+                (self.selected_conversation + offset) % self.conversations.len();
+        }
+    }
+
+    /// Send the message in the selected conversation.
+    /// Returns false if it can't be sent now.
+    fn send(&mut self, text: String) -> bool {
+        let repaint = self.repaint.clone();
+        let Some(conversation) = self.sendable_conversation() else {
+            return false;
+        };
+        conversation.send_user_message(repaint, text);
+        self.scroll = 0;
+        true
+    }
+
+    fn regenerate(&mut self) {
+        let repaint = self.repaint.clone();
+        if let Some(conversation) = self.sendable_conversation() {
+            if !conversation.regenerate(repaint) {
+                self.status = Some("No message to send again".to_string());
+            }
+        }
+        self.scroll = 0;
+    }
+
+    /// The selected conversation, if a message can be sent in it.
+    fn sendable_conversation(&mut self) -> Option<&mut Conversation> {
+        let status = match self.conversations.get_mut(self.selected_conversation) {
+            None => "No conversation, press Ctrl+N to create one",
+            Some(conversation) if conversation.is_busy() => {
+                "The answer is still received, press Ctrl+S to stop it"
+            }
+            Some(conversation) if conversation.throttling().is_some_and(|t| t.is_exhausted()) => {
+                "The conversation's turn limit is reached"
+            }
+            Some(_) => return self.conversations.get_mut(self.selected_conversation),
+        };
+        self.status = Some(status.to_string());
+        None
+    }
+
+    fn add_conversation(&mut self) {
+        if self.add_conversation_handle.is_some() {
+            return;
+        }
+        let kind = self.settings.backend;
+        if !self.backends.is_ready(&self.settings, kind) {
+            self.status = Some(
+                "No cookie, set BING_COOKIE, pass --cookie-file or add one in the GUI".to_string(),
+            );
+            return;
+        }
+
+        let backend = self.backends.get(&self.settings, kind);
+        let repaint = self.repaint.clone();
+        self.add_conversation_handle = Some(tokio::spawn(async move {
+            let conversation = match backend {
+                Ok(backend) => backend.create_conversation().await,
+                Err(e) => Err(e),
+            };
+            repaint();
+            conversation.map(|conversation| Conversation::new(kind, conversation))
+        }));
+    }
+
+    fn close_conversation(&mut self) {
+        if self.selected_conversation < self.conversations.len() {
+            let conversation = self.conversations.remove(self.selected_conversation);
+            if conversation.is_busy() {
+                conversation.stop();
+            }
+            self.selected_conversation = self.selected_conversation.saturating_sub(1);
+        }
+    }
+
+    fn prepare_handles(&mut self) {
+        if let Some(conversation) = self
+            .add_conversation_handle
+            .as_mut()
+            .and_then(|h| h.now_or_never())
+            .and_then(|r| r.ok())
+        {
+            match conversation {
+                Ok(conversation) => {
+                    self.conversations.push(conversation);
+                    self.selected_conversation = self.conversations.len() - 1;
+                    self.scroll = 0;
+                }
+                Err(e) => {
+                    error!("failed to add conversation: {}", e);
+                    self.status = Some(format!("Failed to add conversation: {}", e));
+                }
+            }
+            self.add_conversation_handle = None;
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [tabs_area, info_area, messages_area, input_area, status_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(INPUT_HEIGHT),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let mut titles: Vec<String> = self
+            .conversations
+            .iter()
+            .map(|c| c.id().to_string())
+            .collect();
+        if self.add_conversation_handle.is_some() {
+            titles.push("creating…".to_string());
+        }
+        frame.render_widget(
+            Tabs::new(titles)
+                .select(self.selected_conversation)
+                .highlight_style(Style::new().reversed()),
+            tabs_area,
+        );
+
+        frame.render_widget(Paragraph::new(self.info_line()), info_area);
+        self.draw_messages(frame, messages_area);
+
+        let is_busy = self
+            .conversations
+            .get(self.selected_conversation)
+            .is_some_and(Conversation::is_busy);
+        self.input.set_block(Block::bordered().title(if is_busy {
+            "Input (Ctrl+S to stop)"
+        } else {
+            "Input"
+        }));
+        frame.render_widget(&self.input, input_area);
+
+        let status = match &self.status {
+            Some(status) => Line::from(status.as_str()).red(),
+            None => Line::from(HELP).dark_gray(),
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+
+    /// Style, turns and label of the selected conversation, like the GUI's header.
+    fn info_line(&self) -> Line<'static> {
+        let mut spans = vec![Span::from(format!("Backend: {:?}", self.settings.backend))];
+        if let Some(conversation) = self.conversations.get(self.selected_conversation) {
+            spans.push(" │ ".dark_gray());
+            spans.push(Span::from(format!("Style: {}", conversation.style())));
+            if let Some(throttling) = conversation.throttling() {
+                spans.push(" │ ".dark_gray());
+                spans.push(Span::from(format!(
+                    "{} / {} turns",
+                    throttling.num_user_messages_in_conversation,
+                    throttling.max_num_user_messages_in_conversation
+                )));
+            }
+            if let Some(label) = conversation.label() {
+                spans.push(" │ ".dark_gray());
+                spans.push(Span::from(label.to_string()));
+            }
+        }
+        Line::from(spans)
+    }
+
+    fn draw_messages(&mut self, frame: &mut Frame, area: Rect) {
+        let Some(conversation) = self.conversations.get_mut(self.selected_conversation) else {
+            frame.render_widget(
+                Paragraph::new("No conversations, press Ctrl+N to create one").dark_gray(),
+                area,
+            );
+            return;
+        };
+
+        let messages = conversation.msgs().clone();
+        let is_busy = conversation.is_busy();
+        let mut lines = vec![];
+        {
+            let messages = messages.lock().unwrap();
+            if messages.is_empty() {
+                lines.push(Line::from("No messages yet").dark_gray());
+            }
+            for message in messages.iter() {
+                message_lines(message, area.width, &mut lines);
+            }
+        }
+
+        if is_busy {
+            if let Some(activity) = conversation.activity() {
+                lines.push(Line::from(activity).dark_gray().italic());
+            }
+        } else {
+            for (i, suggestion) in conversation
+                .suggestions()
+                .lock()
+                .unwrap()
+                .iter()
+                .enumerate()
+            {
+                lines.push(Line::from(vec![
+                    format!("Alt+{} ", i + 1).dark_gray(),
+                    Span::from(suggestion.clone()).cyan(),
+                ]));
+            }
+        }
+
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false });
+        // Scrolled to the newest message unless the user scrolled up
+        let max_scroll = u16::try_from(paragraph.line_count(area.width))
+            .unwrap_or(u16::MAX)
+            .saturating_sub(area.height);
+        self.scroll = self.scroll.min(max_scroll);
+        frame.render_widget(paragraph.scroll((max_scroll - self.scroll, 0)), area);
+    }
+}
+
+fn new_input() -> TextArea<'static> {
+    let mut input = TextArea::default();
+    input.set_cursor_line_style(Style::default());
+    input.set_placeholder_text("Type a message");
+    input
+}
+
+/// The value after `current`, wrapping around.
+fn next<T: Copy + PartialEq>(all: &[T], current: T) -> T {
+    let i = all.iter().position(|value| *value == current).unwrap_or(0);
+    all[(i + 1) % all.len()]
+}
+
+fn message_lines(message: &Message, width: u16, lines: &mut Vec<Line<'static>>) {
+    match message {
+        Message::Text {
+            sender,
+            content,
+            sources,
+            retracted,
+        } => {
+            let sender = match sender {
+                Sender::User => "You: ".bold().cyan(),
+                Sender::Bot => "Bot: ".bold().green(),
+            };
+            let mut text = content_lines(content);
+            text[0].spans.insert(0, sender);
+            lines.extend(text);
+
+            if let Some(retracted) = retracted {
+                lines.push(Line::from("Retracted answer:").yellow());
+                lines.extend(
+                    content_lines(retracted)
+                        .into_iter()
+                        .map(|line| line.dark_gray()),
+                );
+            }
+
+            for (i, source) in sources.iter().enumerate() {
+                lines.push(Line::from(vec![
+                    Span::from(format!("[{}] {} ", i + 1, source.provider_display_name)),
+                    Span::from(source.see_more_url.clone()).blue().underlined(),
+                ]));
+            }
+        }
+        Message::Error(error) => lines.push(Line::from(format!("Error: {}", error)).red()),
+        Message::Separator => lines.push(Line::from("─".repeat(width as usize)).dark_gray()),
+    }
+}
+
+/// Lines of a message's text, at least one.
+fn content_lines(content: &str) -> Vec<Line<'static>> {
+    let mut lines: Vec<Line> = display_text(content)
+        .lines()
+        .map(|line| Line::from(line.to_string()))
+        .collect();
+    if lines.is_empty() {
+        lines.push(Line::default());
+    }
+    lines
+}
@@ -0,0 +1,21 @@
+use std::fs::File;
+
+use crate::chat::{data_dir, FileStorage, Settings};
+
+mod app;
+pub use app::*;
+
+/// File the TUI logs to, as the terminal is drawn on.
+pub fn log_file() -> Option<File> {
+    let dir = data_dir()?;
+    std::fs::create_dir_all(&dir).ok()?;
+    File::create(dir.join("tui.log")).ok()
+}
+
+/// Draw the TUI until it's closed, the conversations are saved then.
+pub async fn run(settings: Settings, storage: FileStorage) -> anyhow::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = Application::new(settings, storage).run(&mut terminal).await;
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use crate::bing::{AccountHealth, AccountPool};
+
+use crate::chat::AccountEntry;
+
+/// Window showing the account pool's health and editing its accounts.
+#[derive(Default)]
+pub struct AccountsWindow {
+    /// True while the window is shown.
+    open: bool,
+    /// Name of the account being added.
+    name: String,
+    /// Cookie header of the account being added.
+    cookie: String,
+}
+
+impl AccountsWindow {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// Show the window, returns true if accounts were added or removed.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        pool: &AccountPool,
+        accounts: &mut Vec<AccountEntry>,
+    ) -> bool {
+        let mut changed = false;
+        let mut open = self.open;
+
+        egui::Window::new("Accounts")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let mut remove: Option<String> = None;
+
+                egui::Grid::new("accounts_grid")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for account in pool.accounts() {
+                            ui.label(&account.name);
+                            match account.health() {
+                                AccountHealth::Healthy => {
+                                    ui.label("Healthy");
+                                }
+                                AccountHealth::CoolingDown(left) => {
+                                    ui.colored_label(
+                                        ui.visuals().warn_fg_color,
+                                        format!(
+                                            "Cooling down, {}:{:02} left",
+                                            left.as_secs() / 60,
+                                            left.as_secs() % 60
+                                        ),
+                                    );
+                                    ctx.request_repaint_after(Duration::from_secs(1));
+                                }
+                            }
+                            if ui.button("Remove").clicked() {
+                                remove = Some(account.name.clone());
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                if let Some(name) = remove {
+                    pool.remove(&name);
+                    accounts.retain(|account| account.name != name);
+                    changed = true;
+                }
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label("Name:");
+                    ui.text_edit_singleline(&mut self.name);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Cookie:");
+                    ui.text_edit_singleline(&mut self.cookie);
+                });
+
+                ui.add_enabled_ui(
+                    !self.name.trim().is_empty() && !self.cookie.trim().is_empty(),
+                    |ui| {
+                        if ui.button("Add").clicked() {
+                            let name = self.name.trim().to_string();
+                            let cookie = self.cookie.trim().to_string();
+                            pool.add(name.clone(), cookie.clone());
+                            accounts.retain(|account| account.name != name);
+                            accounts.push(AccountEntry { name, cookie });
+                            self.name.clear();
+                            self.cookie.clear();
+                            changed = true;
+                        }
+                    },
+                );
+            });
+
+        self.open = open;
+        changed
+    }
+}
@@ -0,0 +1,79 @@
+use crate::bing::CookieJar;
+
+/// Host the imported cookies are filtered for.
+const BING_HOST: &str = "www.bing.com";
+
+/// Dialog importing cookies from a browser export.
+#[derive(Default)]
+pub struct CookieImport {
+    /// True while the dialog is shown.
+    open: bool,
+    /// Path of a file to load into `text`.
+    path: String,
+    /// A `Cookie:` header, a Netscape cookies.txt file or a JSON export.
+    text: String,
+    error: Option<String>,
+}
+
+impl CookieImport {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.error = None;
+    }
+
+    /// Show the dialog, returns the `Cookie:` header once cookies are imported.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<String> {
+        let mut imported = None;
+        let mut open = self.open;
+
+        egui::Window::new("Import cookies")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label("Paste a Cookie header, a cookies.txt file or a JSON export:");
+
+                ui.horizontal(|ui| {
+                    ui.label("File:");
+                    ui.text_edit_singleline(&mut self.path);
+                    if ui.button("Load").clicked() {
+                        match std::fs::read_to_string(self.path.trim()) {
+                            Ok(text) => {
+                                self.text = text;
+                                self.error = None;
+                            }
+                            Err(e) => self.error = Some(e.to_string()),
+                        }
+                    }
+                });
+
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut self.text)
+                                .code_editor()
+                                .desired_rows(8),
+                        );
+                    });
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+
+                if ui.button("Import").clicked() {
+                    match CookieJar::parse(&self.text).map(|jar| jar.header_for(BING_HOST)) {
+                        Ok(Some(header)) => imported = Some(header),
+                        Ok(None) => self.error = Some("no cookies for bing.com found".to_string()),
+                        Err(e) => self.error = Some(e.to_string()),
+                    }
+                }
+            });
+
+        if imported.is_some() {
+            open = false;
+            self.text.clear();
+        }
+        self.open = open;
+        imported
+    }
+}
@@ -1,5 +1,5 @@
 mod app;
 pub use app::*;
 
-mod conversation;
-mod settings;
+mod accounts;
+mod cookie_import;
@@ -0,0 +1,116 @@
+#![cfg(feature = "chat")]
+
+use std::{sync::Arc, time::Duration};
+
+use bing_client::{
+    backend::{ChatBackend, EchoBackend},
+    chat::{BackendKind, Conversation, Message, Repaint, Sender},
+};
+
+const LONG_MESSAGE: &str = "one two three four five six seven eight nine ten";
+
+async fn conversation(backend: &EchoBackend) -> Conversation {
+    Conversation::new(
+        BackendKind::Echo,
+        backend.create_conversation().await.unwrap(),
+    )
+}
+
+fn repaint() -> Repaint {
+    Arc::new(|| {})
+}
+
+/// The messages as `You: ...`, `Bot: ...`, `Error: ...` and `---` lines
+fn transcript(conversation: &mut Conversation) -> Vec<String> {
+    conversation
+        .msgs()
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|message| match message {
+            Message::Text {
+                sender: Sender::User,
+                content,
+                ..
+            } => format!("You: {}", content),
+            Message::Text {
+                sender: Sender::Bot,
+                content,
+                ..
+            } => format!("Bot: {}", content),
+            Message::Error(error) => format!("Error: {}", error),
+            Message::Separator => "---".to_string(),
+        })
+        .collect()
+}
+
+/// Wait until the transcript satisfies the condition, at most five seconds
+async fn wait_for(conversation: &mut Conversation, condition: impl Fn(&[String]) -> bool) {
+    for _ in 0..500 {
+        if condition(&transcript(conversation)) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("timed out, transcript: {:?}", transcript(conversation));
+}
+
+async fn wait_until_idle(conversation: &mut Conversation) {
+    for _ in 0..500 {
+        // Reading the messages notices the finished answer
+        conversation.msgs();
+        if !conversation.is_busy() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("the answer didn't finish");
+}
+
+#[tokio::test]
+async fn send_stop_and_regenerate() {
+    let backend = EchoBackend::new().with_delay(Duration::from_millis(20));
+    let mut conversation = conversation(&backend).await;
+
+    conversation.send_user_message(repaint(), "Hello there");
+    wait_until_idle(&mut conversation).await;
+    assert_eq!(
+        transcript(&mut conversation),
+        ["You: Hello there", "Bot: Hello there", "---"]
+    );
+
+    // Stop once the first words arrived
+    conversation.send_user_message(repaint(), LONG_MESSAGE);
+    wait_for(&mut conversation, |transcript| transcript.len() == 5).await;
+    conversation.stop();
+    wait_until_idle(&mut conversation).await;
+    let stopped = transcript(&mut conversation);
+    assert_eq!(stopped.len(), 6);
+    assert_eq!(stopped[3], format!("You: {}", LONG_MESSAGE));
+    let partial = stopped[4].strip_prefix("Bot: ").unwrap();
+    assert!(!partial.is_empty() && LONG_MESSAGE.starts_with(partial));
+    assert_ne!(partial, LONG_MESSAGE);
+    assert_eq!(stopped[5], "---");
+
+    // The last user message is sent again and answered in full
+    assert!(conversation.regenerate(repaint()));
+    wait_until_idle(&mut conversation).await;
+    let regenerated = transcript(&mut conversation);
+    assert_eq!(regenerated[..6], stopped[..]);
+    assert_eq!(
+        regenerated[6..],
+        [
+            format!("You: {}", LONG_MESSAGE),
+            format!("Bot: {}", LONG_MESSAGE),
+            "---".to_string(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn nothing_to_regenerate() {
+    let mut conversation = conversation(&EchoBackend::new()).await;
+    assert!(!conversation.regenerate(repaint()));
+    assert!(!conversation.is_busy());
+    assert!(transcript(&mut conversation).is_empty());
+}
@@ -0,0 +1,478 @@
+use std::time::Duration;
+
+use bing_client::bing::{
+    protocol::{SourceAttribution, Throttling},
+    Activity, ClientConfig, Conversation, ConversationEvent, ConversationStyle, Error, UpdateMode,
+};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+mod mock;
+use mock::*;
+
+const COOKIE: &str = "_U=mock-token";
+
+/// Receive the events of an answer until the channel closes
+async fn events(mut rx: UnboundedReceiver<ConversationEvent>) -> Vec<ConversationEvent> {
+    let mut events = vec![];
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+    })
+    .await
+    .expect("the answer never ended");
+    events
+}
+
+async fn conversation(config: ClientConfig) -> Conversation {
+    Conversation::with_config(COOKIE, config).await.unwrap()
+}
+
+async fn ask(conversation: &mut Conversation, text: &str) -> Vec<ConversationEvent> {
+    events(conversation.send_message(text).await.unwrap()).await
+}
+
+fn last_update(events: &[ConversationEvent]) -> Option<&str> {
+    events.iter().rev().find_map(|event| match event {
+        ConversationEvent::Update(text) => Some(text.as_str()),
+        _ => None,
+    })
+}
+
+#[tokio::test]
+async fn answer_is_streamed_and_completed() {
+    let server = MockServer::start(Script::with_turns([Turn::answer("Hello from the mock")])).await;
+    let mut conversation = conversation(server.config()).await;
+    assert_eq!(conversation.id(), "mock-conversation");
+
+    let events = ask(&mut conversation, "Hi").await;
+    let updates = events
+        .iter()
+        .filter(|event| matches!(event, ConversationEvent::Update(_)))
+        .count();
+    assert_eq!(updates, 4);
+    assert_eq!(last_update(&events), Some("Hello from the mock"));
+    assert!(matches!(events.last(), Some(ConversationEvent::Complete)));
+
+    let log = server.log();
+    assert_eq!(log.cookies, [COOKIE]);
+    assert_eq!(log.connections, 1);
+    assert_eq!(log.requests.len(), 1);
+    let request = &log.requests[0];
+    assert_eq!(request.message.text, "Hi");
+    assert_eq!(request.conversation_id, "mock-conversation");
+    assert_eq!(request.conversation_signature, "mock-signature");
+    assert_eq!(request.participant.id, "mock-client");
+    assert!(request.is_start_of_session);
+    assert!(log.pings >= 1);
+}
+
+#[tokio::test]
+async fn second_message_continues_the_session() {
+    let server = MockServer::start(Script::with_turns([
+        Turn::answer("First"),
+        Turn::answer("Second"),
+    ]))
+    .await;
+    let mut conversation = conversation(server.config()).await;
+
+    ask(&mut conversation, "One").await;
+    let events = ask(&mut conversation, "Two").await;
+    assert_eq!(last_update(&events), Some("Second"));
+
+    let log = server.log();
+    assert_eq!(log.connections, 2);
+    assert!(log.requests[0].is_start_of_session);
+    assert!(!log.requests[1].is_start_of_session);
+}
+
+#[tokio::test]
+async fn persistent_connection_is_reused() {
+    let server = MockServer::start(Script::with_turns([
+        Turn::answer("First"),
+        Turn::answer("Second"),
+    ]))
+    .await;
+    let config = ClientConfig {
+        persistent: true,
+        ..server.config()
+    };
+    let mut conversation = conversation(config).await;
+
+    ask(&mut conversation, "One").await;
+    ask(&mut conversation, "Two").await;
+    assert_eq!(server.log().connections, 1);
+}
+
+#[tokio::test]
+async fn style_is_sent_with_the_message() {
+    let server = MockServer::start(Script::with_turns([Turn::answer("Precisely")])).await;
+    let mut conversation = conversation(server.config()).await;
+
+    let rx = conversation
+        .send_message_with_style("Hi", ConversationStyle::Precise)
+        .await
+        .unwrap();
+    events(rx).await;
+    assert_eq!(
+        server.log().requests[0].options_sets,
+        ConversationStyle::Precise.options_sets()
+    );
+}
+
+#[tokio::test]
+async fn delta_mode_reports_appended_text() {
+    let turn = Turn::default()
+        .update("Hello")
+        .update("Hello world")
+        .update("Goodbye world")
+        .item_and_complete(answer_item("Goodbye world"));
+    let server = MockServer::start(Script::with_turns([turn])).await;
+    let config = ClientConfig {
+        update_mode: UpdateMode::Delta,
+        ..server.config()
+    };
+    let mut conversation = conversation(config).await;
+
+    let events = ask(&mut conversation, "Hi").await;
+    assert!(matches!(&events[0], ConversationEvent::Delta(text) if text == "Hello"));
+    assert!(matches!(&events[1], ConversationEvent::Delta(text) if text == " world"));
+    assert!(matches!(&events[2], ConversationEvent::Rewrite(text) if text == "Goodbye world"));
+}
+
+#[tokio::test]
+async fn final_item_reports_sources_and_suggestions() {
+    let mut item = answer_item("See [^1^]");
+    item.messages[0].source_attributions = vec![SourceAttribution {
+        provider_display_name: "Example".to_string(),
+        see_more_url: "https://example.com/".to_string(),
+        search_query: None,
+    }];
+    item.messages[0].suggested_responses = suggestions(&["Tell me more", "Thanks"]);
+    let turn = Turn::default()
+        .activity("InternalSearchQuery", "mock search")
+        .activity("InternalSearchQuery", "mock search")
+        .update("See [^1^]")
+        .item_and_complete(item);
+    let server = MockServer::start(Script::with_turns([turn])).await;
+    let mut conversation = conversation(server.config()).await;
+
+    let events = ask(&mut conversation, "Search").await;
+    let activities: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            ConversationEvent::Progress(activity) => Some(activity),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(activities.len(), 1, "repeated activities are reported once");
+    assert!(matches!(activities[0], Activity::Searching(query) if query == "mock search"));
+    assert!(events.iter().any(|event| matches!(
+        event,
+        ConversationEvent::Sources(sources) if sources[0].provider_display_name == "Example"
+    )));
+    assert!(events.iter().any(|event| matches!(
+        event,
+        ConversationEvent::Suggestions(suggestions) if suggestions == &["Tell me more", "Thanks"]
+    )));
+}
+
+#[tokio::test]
+async fn pings_and_malformed_frames_are_skipped() {
+    let turn = Turn::default()
+        .ping()
+        .raw("not json\u{1e}")
+        .update("Still")
+        .ping()
+        .item_and_complete(answer_item("Still here"));
+    let server = MockServer::start(Script::with_turns([turn])).await;
+    let mut conversation = conversation(server.config()).await;
+
+    let events = ask(&mut conversation, "Hi").await;
+    assert!(matches!(events.last(), Some(ConversationEvent::Complete)));
+}
+
+#[tokio::test]
+async fn retracted_answer_keeps_the_original() {
+    let mut apology = mock::bot_message("Sorry, let's talk about something else.");
+    apology.content_origin = Some("Apology".to_string());
+    let turn = Turn::default()
+        .update("Something I shouldn't say")
+        .invocation(apology.clone())
+        .item_and_complete(answer_item("Sorry, let's talk about something else."));
+    let server = MockServer::start(Script::with_turns([turn])).await;
+    let mut conversation = conversation(server.config()).await;
+
+    let events = ask(&mut conversation, "Hi").await;
+    assert!(events.iter().any(|event| matches!(
+        event,
+        ConversationEvent::Retracted { original, apology }
+            if original == "Something I shouldn't say" && apology.starts_with("Sorry")
+    )));
+}
+
+#[tokio::test]
+async fn throttling_is_tracked_and_enforced() {
+    let mut item = answer_item("Last one");
+    item.throttling = Some(Throttling {
+        max_num_user_messages_in_conversation: 2,
+        num_user_messages_in_conversation: 2,
+    });
+    let turn = Turn::default()
+        .throttling(1, 2)
+        .update("Last one")
+        .item_and_complete(item);
+    let server = MockServer::start(Script::with_turns([turn])).await;
+    let mut conversation = conversation(server.config()).await;
+
+    let events = ask(&mut conversation, "Hi").await;
+    let counters: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            ConversationEvent::Throttling(throttling) => {
+                Some(throttling.num_user_messages_in_conversation)
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(counters, [1, 2]);
+    assert!(conversation.throttling().unwrap().is_exhausted());
+
+    let err = conversation.send_message("Again").await.unwrap_err();
+    assert!(matches!(err, Error::TurnLimitReached(2)));
+    assert_eq!(server.log().requests.len(), 1);
+}
+
+#[tokio::test]
+async fn failed_result_ends_the_answer() {
+    let turn = Turn::default()
+        .update("Partial")
+        .item_and_complete(failed_item("Throttled", "too many requests"));
+    let server = MockServer::start(Script::with_turns([turn])).await;
+    let mut conversation = conversation(server.config()).await;
+
+    let events = ask(&mut conversation, "Hi").await;
+    assert!(matches!(
+        events.last(),
+        Some(ConversationEvent::Failed { partial, error: Error::Throttled(message) })
+            if partial == "Partial" && message == "too many requests"
+    ));
+}
+
+#[tokio::test]
+async fn expiry_time_is_kept_and_enforced() {
+    let mut item = answer_item("Bye");
+    item.conversation_expiry_time = Some("2000-01-01T00:00:00Z".to_string());
+    let turn = Turn::default().update("Bye").item_and_complete(item);
+    let server = MockServer::start(Script::with_turns([turn])).await;
+    let mut conversation = conversation(server.config()).await;
+
+    ask(&mut conversation, "Hi").await;
+    assert!(conversation.state().is_expired());
+    let err = conversation.send_message("Again").await.unwrap_err();
+    assert!(matches!(err, Error::ConversationExpired));
+    assert!(matches!(
+        Conversation::restore(conversation.state(), server.config()),
+        Err(Error::ConversationExpired)
+    ));
+}
+
+#[tokio::test]
+async fn restored_conversation_continues() {
+    let server = MockServer::start(Script::with_turns([
+        Turn::answer("First"),
+        Turn::answer("Second"),
+    ]))
+    .await;
+    let mut conversation = conversation(server.config()).await;
+    ask(&mut conversation, "One").await;
+
+    let mut restored = Conversation::restore(conversation.state(), server.config()).unwrap();
+    let events = ask(&mut restored, "Two").await;
+    assert_eq!(last_update(&events), Some("Second"));
+
+    let log = server.log();
+    assert_eq!(
+        log.cookies.len(),
+        1,
+        "restoring doesn't create a conversation"
+    );
+    assert_eq!(log.requests[1].conversation_id, "mock-conversation");
+    assert!(!log.requests[1].is_start_of_session);
+}
+
+#[tokio::test]
+async fn drop_before_the_first_token_is_retried() {
+    let server = MockServer::start(Script::with_turns([
+        Turn::default().disconnect(),
+        Turn::answer("Second try"),
+    ]))
+    .await;
+    let mut conversation = conversation(server.config()).await;
+
+    let events = ask(&mut conversation, "Hi").await;
+    assert_eq!(last_update(&events), Some("Second try"));
+    assert_eq!(server.log().connections, 2);
+    assert_eq!(server.log().requests.len(), 2);
+}
+
+#[tokio::test]
+async fn drop_after_the_first_token_fails_with_the_partial_answer() {
+    let turn = Turn::default().update("Half an").disconnect();
+    let server = MockServer::start(Script::with_turns([turn, Turn::answer("unused")])).await;
+    let mut conversation = conversation(server.config()).await;
+
+    let events = ask(&mut conversation, "Hi").await;
+    assert!(matches!(
+        events.last(),
+        Some(ConversationEvent::Failed { partial, error: Error::Ws(_) }) if partial == "Half an"
+    ));
+    assert_eq!(server.log().connections, 1);
+}
+
+#[tokio::test]
+async fn server_close_fails_the_answer() {
+    let turn = Turn::default().update("Closing").close("mock shutdown");
+    let server = MockServer::start(Script::with_turns([turn])).await;
+    let mut conversation = conversation(server.config()).await;
+
+    let events = ask(&mut conversation, "Hi").await;
+    assert!(matches!(
+        events.last(),
+        Some(ConversationEvent::Failed {
+            error: Error::ConnectionClosed,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn silent_server_times_out() {
+    let server = MockServer::start(Script::with_turns([
+        Turn::default().delay(Duration::from_secs(5)),
+        Turn::default().update("Slow").delay(Duration::from_secs(5)),
+    ]))
+    .await;
+    let mut config = server.config();
+    config.timeouts.first_token = Some(Duration::from_millis(200));
+    config.timeouts.idle = Some(Duration::from_millis(200));
+    let mut conversation = conversation(config).await;
+
+    let events = ask(&mut conversation, "Hi").await;
+    assert!(matches!(
+        events.last(),
+        Some(ConversationEvent::Failed {
+            error: Error::FirstTokenTimeout,
+            ..
+        })
+    ));
+    // The invocation counts against the limits, so it isn't sent again
+    assert_eq!(server.log().requests.len(), 1);
+
+    let events = ask(&mut conversation, "Hi again").await;
+    assert!(matches!(
+        events.last(),
+        Some(ConversationEvent::Failed { partial, error: Error::IdleTimeout }) if partial == "Slow"
+    ));
+}
+
+#[tokio::test]
+async fn cancel_stops_the_invocation() {
+    let turn = Turn::default()
+        .update("Thinking")
+        .delay(Duration::from_secs(5))
+        .item_and_complete(answer_item("Too late"));
+    let server = MockServer::start(Script::with_turns([turn])).await;
+    let mut conversation = conversation(server.config()).await;
+
+    let mut rx = conversation.send_message("Hi").await.unwrap();
+    assert!(matches!(
+        rx.recv().await,
+        Some(ConversationEvent::Update(_))
+    ));
+    assert!(conversation.is_busy().await);
+    assert!(conversation.cancel());
+
+    let events = events(rx).await;
+    assert!(matches!(events.last(), Some(ConversationEvent::Cancelled)));
+    assert!(server.wait_for(|log| log.cancellations.len() == 1).await);
+    assert!(!conversation.is_busy().await);
+}
+
+#[tokio::test]
+async fn busy_conversation_rejects_messages() {
+    let turn = Turn::default()
+        .update("Busy")
+        .delay(Duration::from_millis(300))
+        .item_and_complete(answer_item("Busy"));
+    let server = MockServer::start(Script::with_turns([turn])).await;
+    let mut conversation = conversation(server.config()).await;
+
+    let rx = conversation.send_message("Hi").await.unwrap();
+    assert!(matches!(
+        conversation.send_message("Again").await,
+        Err(Error::WsBusy)
+    ));
+    events(rx).await;
+}
+
+#[tokio::test]
+async fn handshake_error_fails_the_message() {
+    let server = MockServer::start(Script {
+        handshake_error: Some("mock refuses".to_string()),
+        ..Default::default()
+    })
+    .await;
+    let mut config = server.config();
+    config.retry.max_retries = 0;
+    let mut conversation = conversation(config).await;
+
+    let err = conversation.send_message("Hi").await.unwrap_err();
+    assert!(matches!(err, Error::Init));
+    assert_eq!(server.log().connections, 0);
+}
+
+#[tokio::test]
+async fn create_result_is_reported() {
+    let server = MockServer::start(Script {
+        create: Create::Result("UnauthorizedRequest".to_string()),
+        ..Default::default()
+    })
+    .await;
+    let err = Conversation::with_config(COOKIE, server.config())
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(err, Error::Unauthorized(_)));
+    assert!(err.is_account_failure());
+}
+
+#[tokio::test]
+async fn create_status_is_reported() {
+    let server = MockServer::start(Script {
+        create: Create::Status(403),
+        ..Default::default()
+    })
+    .await;
+    let err = Conversation::with_config(COOKIE, server.config())
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(&err, Error::Http(err) if err.status().map(|s| s.as_u16()) == Some(403)));
+}
+
+#[tokio::test]
+async fn slow_create_times_out() {
+    let server = MockServer::start(Script {
+        create: Create::Delayed(Duration::from_secs(5), Box::default()),
+        ..Default::default()
+    })
+    .await;
+    let mut config = server.config();
+    config.timeouts.create = Some(Duration::from_millis(200));
+    let err = Conversation::with_config(COOKIE, config)
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(err, Error::CreateTimeout));
+}
@@ -0,0 +1,474 @@
+//! A local stand-in for the create endpoint and the ChatHub,
+//! answering with canned frames so the client can be tested offline.
+
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_tungstenite::tungstenite::Message;
+use bing_client::bing::{
+    protocol::{
+        encode, split_frames, BotMessage, ChatRequest, ChatResponse, ChatResult, Close, Completion,
+        Frame, Handshake, HandshakeResponse, Invocation, StreamItem, SuggestedResponse, Throttling,
+        Update,
+    },
+    ClientConfig, RetryConfig, TimeoutConfig,
+};
+use futures::{SinkExt, StreamExt};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, StatusCode,
+};
+use tokio::{net::TcpListener, sync::mpsc};
+
+/// How the create endpoint answers
+#[derive(Debug, Clone)]
+pub enum Create {
+    /// A new conversation with this id
+    Conversation(String),
+    /// No conversation, only a result like `UnauthorizedRequest`
+    Result(String),
+    /// An empty body with this status
+    Status(u16),
+    /// Wait before answering
+    Delayed(Duration, Box<Create>),
+}
+
+impl Default for Create {
+    fn default() -> Self {
+        Self::Conversation("mock-conversation".to_string())
+    }
+}
+
+/// A single action of the ChatHub while answering a message
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// Send the frames in a single websocket message
+    Send(Vec<Frame>),
+    /// Send a raw text message, for malformed frames
+    Raw(String),
+    Delay(Duration),
+    /// Drop the connection without a close handshake
+    Disconnect,
+}
+
+/// What the ChatHub does after receiving a message
+#[derive(Debug, Clone, Default)]
+pub struct Turn {
+    pub steps: Vec<Step>,
+}
+
+impl Turn {
+    /// Stream the answer word by word, then send the final item and the completion
+    pub fn answer(text: &str) -> Self {
+        let mut turn = Self::default();
+        let mut partial = String::new();
+        for word in text.split_inclusive(' ') {
+            partial.push_str(word);
+            turn = turn.update(partial.trim());
+        }
+        turn.item_and_complete(answer_item(text))
+    }
+
+    /// A type 1 update with the text of the answer so far
+    pub fn update(self, text: &str) -> Self {
+        self.invocation(bot_message(text))
+    }
+
+    /// A type 1 update with an internal message, like `InternalSearchQuery`
+    pub fn activity(self, message_type: &str, text: &str) -> Self {
+        self.invocation(BotMessage {
+            message_type: Some(message_type.to_string()),
+            ..bot_message(text)
+        })
+    }
+
+    /// A type 1 update with the message
+    pub fn invocation(self, message: BotMessage) -> Self {
+        self.send(Frame::Invocation(Invocation {
+            invocation_id: None,
+            target: "update".to_string(),
+            arguments: vec![Update {
+                messages: vec![message],
+                ..Default::default()
+            }],
+        }))
+    }
+
+    /// A type 1 update carrying only the turn counters
+    pub fn throttling(self, used: u32, max: u32) -> Self {
+        self.send(Frame::Invocation(Invocation {
+            invocation_id: None,
+            target: "update".to_string(),
+            arguments: vec![Update {
+                throttling: Some(Throttling {
+                    max_num_user_messages_in_conversation: max,
+                    num_user_messages_in_conversation: used,
+                }),
+                ..Default::default()
+            }],
+        }))
+    }
+
+    /// A type 2 item and the type 3 completion, in one websocket message
+    pub fn item_and_complete(mut self, item: ChatResponse) -> Self {
+        self.steps.push(Step::Send(vec![
+            Frame::StreamItem(StreamItem {
+                invocation_id: String::new(),
+                item,
+            }),
+            Frame::Completion(Completion::default()),
+        ]));
+        self
+    }
+
+    /// A type 6 ping
+    pub fn ping(self) -> Self {
+        self.send(Frame::Ping)
+    }
+
+    /// A type 7 close
+    pub fn close(self, error: &str) -> Self {
+        self.send(Frame::Close(Close {
+            error: Some(error.to_string()),
+            allow_reconnect: Some(true),
+        }))
+    }
+
+    pub fn raw(mut self, text: &str) -> Self {
+        self.steps.push(Step::Raw(text.to_string()));
+        self
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.steps.push(Step::Delay(delay));
+        self
+    }
+
+    pub fn disconnect(mut self) -> Self {
+        self.steps.push(Step::Disconnect);
+        self
+    }
+
+    fn send(mut self, frame: Frame) -> Self {
+        self.steps.push(Step::Send(vec![frame]));
+        self
+    }
+}
+
+/// A bot message with the text
+pub fn bot_message(text: &str) -> BotMessage {
+    BotMessage {
+        text: Some(text.to_string()),
+        author: "bot".to_string(),
+        ..Default::default()
+    }
+}
+
+/// A successful final item with the answer
+pub fn answer_item(text: &str) -> ChatResponse {
+    ChatResponse {
+        messages: vec![bot_message(text)],
+        result: Some(ChatResult {
+            value: "Success".to_string(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// A final item failing with the result, like `Throttled`
+pub fn failed_item(value: &str, message: &str) -> ChatResponse {
+    ChatResponse {
+        result: Some(ChatResult {
+            value: value.to_string(),
+            message: Some(message.to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Suggested responses of a bot message
+pub fn suggestions(texts: &[&str]) -> Vec<SuggestedResponse> {
+    texts
+        .iter()
+        .map(|text| SuggestedResponse {
+            text: text.to_string(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// How the mock answers
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    pub create: Create,
+    /// Sent instead of the empty handshake response
+    pub handshake_error: Option<String>,
+    /// Used in order, one for every message received on any connection.
+    /// Nothing is sent once they are used up
+    pub turns: Vec<Turn>,
+}
+
+impl Script {
+    pub fn with_turns(turns: impl IntoIterator<Item = Turn>) -> Self {
+        Self {
+            turns: turns.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// What the mock received
+#[derive(Debug, Default)]
+pub struct Log {
+    /// Cookie headers of the create requests
+    pub cookies: Vec<String>,
+    /// Websocket connections that completed the handshake
+    pub connections: usize,
+    /// Messages received, in order
+    pub requests: Vec<ChatRequest>,
+    /// Ids of the cancelled invocations
+    pub cancellations: Vec<String>,
+    pub pings: usize,
+}
+
+struct Shared {
+    script: Script,
+    turns: Mutex<VecDeque<Turn>>,
+    log: Mutex<Log>,
+}
+
+pub struct MockServer {
+    pub create_url: String,
+    pub chathub_url: String,
+    shared: Arc<Shared>,
+}
+
+impl MockServer {
+    /// Listen on random local ports until the runtime shuts down
+    pub async fn start(script: Script) -> Self {
+        let shared = Arc::new(Shared {
+            turns: Mutex::new(script.turns.iter().cloned().collect()),
+            script,
+            log: Mutex::default(),
+        });
+
+        let create_server = {
+            let shared = shared.clone();
+            hyper::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service_fn(
+                move |_| {
+                    let shared = shared.clone();
+                    async move {
+                        Ok::<_, Infallible>(service_fn(move |request| {
+                            let shared = shared.clone();
+                            async move { Ok::<_, Infallible>(create(request, &shared).await) }
+                        }))
+                    }
+                },
+            ))
+        };
+        let create_url = format!(
+            "http://{}/turing/conversation/create",
+            create_server.local_addr()
+        );
+        tokio::spawn(create_server);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let chathub_url = format!("ws://{}/sydney/ChatHub", listener.local_addr().unwrap());
+        let hub_shared = shared.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(chathub(socket, hub_shared.clone()));
+            }
+        });
+
+        Self {
+            create_url,
+            chathub_url,
+            shared,
+        }
+    }
+
+    /// A configuration pointing at the mock, with short timeouts and quick retries
+    pub fn config(&self) -> ClientConfig {
+        ClientConfig {
+            create_url: self.create_url.clone(),
+            chathub_url: self.chathub_url.clone(),
+            retry: RetryConfig {
+                max_retries: 1,
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(10),
+            },
+            timeouts: TimeoutConfig {
+                create: Some(Duration::from_secs(2)),
+                handshake: Some(Duration::from_secs(2)),
+                first_token: Some(Duration::from_secs(2)),
+                idle: Some(Duration::from_secs(2)),
+            },
+            ..Default::default()
+        }
+    }
+
+    pub fn log(&self) -> std::sync::MutexGuard<'_, Log> {
+        self.shared.log.lock().unwrap()
+    }
+
+    /// Wait until the log satisfies the condition, at most a second
+    pub async fn wait_for(&self, condition: impl Fn(&Log) -> bool) -> bool {
+        for _ in 0..100 {
+            if condition(&self.log()) {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        false
+    }
+}
+
+async fn create(request: Request<Body>, shared: &Shared) -> Response<Body> {
+    let cookie = request
+        .headers()
+        .get("cookie")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    shared.log.lock().unwrap().cookies.push(cookie);
+
+    let mut create = shared.script.create.clone();
+    while let Create::Delayed(delay, then) = create {
+        tokio::time::sleep(delay).await;
+        create = *then;
+    }
+    let (status, body) = match create {
+        Create::Conversation(id) => (
+            StatusCode::OK,
+            serde_json::json!({
+                "conversationId": id,
+                "clientId": "mock-client",
+                "conversationSignature": "mock-signature",
+                "result": { "value": "Success", "message": null },
+            })
+            .to_string(),
+        ),
+        Create::Result(value) => (
+            StatusCode::OK,
+            serde_json::json!({
+                "result": { "value": value, "message": "refused by the mock" },
+            })
+            .to_string(),
+        ),
+        Create::Status(status) => (StatusCode::from_u16(status).unwrap(), String::new()),
+        Create::Delayed(..) => unreachable!(),
+    };
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(body.into())
+        .unwrap()
+}
+
+async fn chathub(socket: tokio::net::TcpStream, shared: Arc<Shared>) {
+    let Ok(ws) = async_tungstenite::tokio::accept_async(socket).await else {
+        return;
+    };
+    let (mut sink, mut stream) = ws.split();
+
+    // The client starts with `{"protocol":"json","version":1}`
+    let Some(Ok(message)) = stream.next().await else {
+        return;
+    };
+    let Ok(text) = message.into_text() else {
+        return;
+    };
+    let handshake: Handshake =
+        serde_json::from_str(split_frames(&text).next().unwrap_or_default()).unwrap();
+    assert_eq!(handshake, Handshake::default());
+    let response = HandshakeResponse {
+        error: shared.script.handshake_error.clone(),
+    };
+    if sink.send(text_message(&response)).await.is_err() || response.error.is_some() {
+        return;
+    }
+    shared.log.lock().unwrap().connections += 1;
+
+    let (invocations, mut invoked) = mpsc::unbounded_channel();
+    let reader_shared = shared.clone();
+    let reader = tokio::spawn(async move {
+        // Frames come as binary messages, the close message has no text
+        while let Some(Ok(message)) = stream.next().await {
+            let Ok(text) = message.into_text() else {
+                continue;
+            };
+            for frame in split_frames(&text) {
+                let frame: Frame = serde_json::from_str(frame).unwrap();
+                let mut log = reader_shared.log.lock().unwrap();
+                match frame {
+                    Frame::StreamInvocation(mut invocation) => {
+                        log.requests.push(invocation.arguments.remove(0));
+                        invocations.send(invocation.invocation_id).ok();
+                    }
+                    Frame::CancelInvocation(cancel) => log.cancellations.push(cancel.invocation_id),
+                    Frame::Ping => log.pings += 1,
+                    frame => panic!("unexpected frame from the client: {:?}", frame),
+                }
+            }
+        }
+    });
+
+    while let Some(invocation_id) = invoked.recv().await {
+        let turn = shared.turns.lock().unwrap().pop_front().unwrap_or_default();
+        for step in turn.steps {
+            let message = match step {
+                Step::Send(frames) => {
+                    let mut text = String::new();
+                    for frame in frames {
+                        let frame = with_invocation_id(frame, &invocation_id);
+                        text.push_str(std::str::from_utf8(&encode(&frame).unwrap()).unwrap());
+                    }
+                    Message::Text(text)
+                }
+                Step::Raw(text) => Message::Text(text),
+                Step::Delay(delay) => {
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                Step::Disconnect => {
+                    reader.abort();
+                    return;
+                }
+            };
+            if sink.send(message).await.is_err() {
+                reader.abort();
+                return;
+            }
+        }
+    }
+}
+
+fn with_invocation_id(frame: Frame, id: &str) -> Frame {
+    match frame {
+        Frame::Invocation(invocation) => Frame::Invocation(Invocation {
+            invocation_id: Some(id.to_string()),
+            ..invocation
+        }),
+        Frame::StreamItem(item) => Frame::StreamItem(StreamItem {
+            invocation_id: id.to_string(),
+            ..item
+        }),
+        Frame::Completion(completion) => Frame::Completion(Completion {
+            invocation_id: id.to_string(),
+            ..completion
+        }),
+        frame => frame,
+    }
+}
+
+fn text_message<T: serde::Serialize>(value: &T) -> Message {
+    Message::Text(String::from_utf8(encode(value).unwrap()).unwrap())
+}